@@ -0,0 +1,139 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const APP_NAME: &str = "tetoris";
+const CONFIG_FILE: &str = "config.toml";
+
+/// `$<var>/tetoris`, falling back to `~/<fallback>/tetoris`
+fn app_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    let base = std::env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))?;
+    Some(base.join(APP_NAME))
+}
+
+/// Directory holding our config files: `$XDG_CONFIG_HOME/tetoris`, falling back
+/// to `~/.config/tetoris`
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Directory holding high scores and other saved data: `$XDG_DATA_HOME/tetoris`,
+/// falling back to `~/.local/share/tetoris`
+pub fn data_dir() -> Option<PathBuf> {
+    app_dir("XDG_DATA_HOME", ".local/share")
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Read the config file, `None` when it doesn't exist yet
+pub fn read() -> io::Result<Option<String>> {
+    read_file(config_path())
+}
+
+/// Write the config file, creating its directory if needed
+pub fn write(contents: &str) -> io::Result<()> {
+    write_file(config_path(), contents)
+}
+
+/// Read a file from the data directory, `None` when it doesn't exist yet
+pub fn read_data(name: &str) -> io::Result<Option<String>> {
+    read_file(data_dir().map(|dir| dir.join(name)))
+}
+
+/// Write a file to the data directory, creating the directory if needed
+pub fn write_data(name: &str, contents: &str) -> io::Result<()> {
+    write_file(data_dir().map(|dir| dir.join(name)), contents)
+}
+
+/// Write a new file to the data directory, failing with `AlreadyExists` rather
+/// than replacing a file of the same name
+pub fn create_data(name: &str, contents: &str) -> io::Result<()> {
+    let path = data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?
+        .join(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+/// Rename a file in the data directory, failing with `AlreadyExists` rather
+/// than replacing a file of the new name
+pub fn rename_data(from: &str, to: &str) -> io::Result<()> {
+    let dir =
+        data_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    rename_file(&dir.join(from), &dir.join(to))
+}
+
+fn rename_file(from: &Path, to: &Path) -> io::Result<()> {
+    if to.try_exists()? {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    fs::rename(from, to)
+}
+
+/// Only a missing file counts as not there yet. Any other failure, like a file
+/// that isn't UTF-8 or can't be opened, is an error so it won't be saved over
+fn read_file(path: Option<PathBuf>) -> io::Result<Option<String>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn write_file(path: Option<PathBuf>, contents: &str) -> io::Result<()> {
+    let path = path.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_missing_files_read_as_none() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join(format!("{}-missing-{}.toml", APP_NAME, std::process::id()));
+
+        assert!(read_file(Some(missing)).unwrap().is_none());
+        assert!(read_file(None).unwrap().is_none());
+        assert!(read_file(Some(dir)).is_err());
+    }
+
+    #[test]
+    fn renames_never_replace_a_file() {
+        let dir = std::env::temp_dir().join(format!("{}-rename-{}", APP_NAME, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (from, to) = (dir.join("from"), dir.join("to"));
+        fs::write(&from, "new").unwrap();
+        fs::write(&to, "old").unwrap();
+
+        let err = rename_file(&from, &to).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&to).unwrap(), "old");
+
+        fs::remove_file(&to).unwrap();
+        rename_file(&from, &to).unwrap();
+        assert_eq!(fs::read_to_string(&to).unwrap(), "new");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
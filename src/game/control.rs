@@ -1,70 +1,300 @@
-use crate::game::shape::{Shape, ShapeGenerator};
-use crossterm::style::Color;
+use crate::game::garbage::GarbageGenerator;
+use crate::game::gravity::Gravity;
+use crate::game::mode::GameMode;
+use crate::game::randomizer::RandomizerKind;
+use crate::game::replay::Input;
+use crate::game::scoring::{self, ClearEvent, TSpin};
+use crate::game::shape::{tetrominoes, Orientation, PieceKind, Rotation, Shape, ShapeGenerator};
+use crate::game::srs;
+use crate::game::stats::Stats;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
-pub const ROWS: usize = 20;
-pub const COLS: usize = 11;
+
+const LINES_PER_LEVEL: u32 = 10;
+
+/// Garbage rows kept on the board while digging
+const DIG_GARBAGE_ROWS: usize = 10;
+
+/// Most upcoming shapes the game keeps visible
+pub const NEXT_QUEUE_SIZE: usize = 6;
+
+/// Playfield dimensions: `height` visible rows with `buffer` hidden rows stacked
+/// on top of them. Row 0 of the table is the topmost hidden row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardSize {
+    pub width: usize,
+    pub height: usize,
+    pub buffer: usize,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        Self {
+            width: 10,
+            height: 20,
+            buffer: 20,
+        }
+    }
+}
+
+impl BoardSize {
+    /// Narrowest or shortest board, every shape still fits on it
+    pub const MIN_SIDE: usize = 4;
+    /// Widest or tallest board
+    pub const MAX_SIDE: usize = 100;
+    /// Most hidden rows above the visible field
+    pub const MAX_BUFFER: usize = 100;
+
+    /// Whether the board is within the limits above
+    pub fn is_supported(&self) -> bool {
+        let sides = Self::MIN_SIDE..=Self::MAX_SIDE;
+        sides.contains(&self.width)
+            && sides.contains(&self.height)
+            && self.buffer <= Self::MAX_BUFFER
+    }
+
+    /// Total number of rows in the table, hidden buffer included
+    pub fn rows(&self) -> usize {
+        self.height + self.buffer
+    }
+}
+
+/// Rules fixed for a whole game: they change how pieces are dealt and fall, so
+/// results are only comparable between games sharing a ruleset. Fields missing
+/// from older files take their defaults, which is what those games played with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ruleset {
+    pub gravity: Gravity,
+    pub randomizer: RandomizerKind,
+    /// Chance in percent that a garbage hole changes column, see `GarbageGenerator`
+    pub messiness: u32,
+    /// How long a grounded shape waits before it locks
+    pub lock_delay_ms: u64,
+    /// Moves and rotations that may restart the lock delay before the shape
+    /// locks anyway, see `Game::update_lock_delay`
+    pub max_lock_resets: u32,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            gravity: Gravity::Guideline,
+            randomizer: RandomizerKind::default(),
+            messiness: 100,
+            lock_delay_ms: 500,
+            max_lock_resets: 15,
+        }
+    }
+}
+
+impl Ruleset {
+    /// Short name such as `guideline/7-bag/100`, used to keep results apart. A
+    /// lock delay other than the guideline one is appended, e.g. `/300ms/10`,
+    /// so names from before it could change still match
+    pub fn name(&self) -> String {
+        let name = format!(
+            "{}/{}/{}",
+            self.gravity.name(),
+            self.randomizer.name(),
+            self.messiness
+        );
+        let default = Self::default();
+        if (self.lock_delay_ms, self.max_lock_resets)
+            == (default.lock_delay_ms, default.max_lock_resets)
+        {
+            name
+        } else {
+            format!("{}/{}ms/{}", name, self.lock_delay_ms, self.max_lock_resets)
+        }
+    }
+
+    pub fn lock_delay(&self) -> Duration {
+        Duration::from_millis(self.lock_delay_ms)
+    }
+}
+
+/// A single square of the locked stack, remembering which piece left it there
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Filled(PieceKind),
+    /// Part of a garbage row pushed up from below
+    Garbage,
+}
+
+impl Cell {
+    pub fn is_filled(self) -> bool {
+        self != Cell::Empty
+    }
+}
+
+/// Why a game ended, named after the guideline terms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum GameOverReason {
+    /// A new shape spawned overlapping the stack
+    BlockOut,
+    /// A shape locked entirely inside the hidden buffer
+    LockOut,
+    /// The stack grew past the top of the buffer
+    TopOut,
+}
+
+impl GameOverReason {
+    pub fn description(self) -> &'static str {
+        match self {
+            GameOverReason::BlockOut => "Block out: no room to spawn the next piece",
+            GameOverReason::LockOut => "Lock out: a piece locked above the playfield",
+            GameOverReason::TopOut => "Top out: the stack reached the ceiling",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    Playing,
+    Over(GameOverReason),
+    /// The game mode's goal was reached
+    Complete,
+}
+
+/// The last action that successfully changed the current shape's position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Spawn,
+    Move,
+    Rotate { rotation: Rotation, kick: usize },
+}
 
 pub struct Game {
-    pub table: [[u8; COLS]; ROWS],
+    pub board: BoardSize,
+    pub table: Vec<Vec<Cell>>,
     pub current: Shape,
-    pub next_shape: Shape,
+    pub next_queue: Vec<Shape>, // Upcoming shapes, the first one spawns next
+    pub hold: Option<Shape>,
+    pub hold_used: bool, // Hold may only be used once until the current piece locks
     pub score: u32,
-    pub running: bool,
+    pub level: u32,
+    pub lines: u32,
+    pub stats: Stats,
+    pub last_action: Action,
+    pub last_clear: Option<ClearEvent>,
+    pub combo: Option<u32>, // Consecutive clearing locks, None once a lock clears nothing
+    pub back_to_back: bool, // The last line clear was a tetris or a T-spin
+    pub state: GameState,
+    pub mode: GameMode,
     pub paused: bool,
-    pub last_tick: Instant,
+    pub time: Duration,       // Game clock: play time so far, paused time excluded
+    pub last_update: Instant, // Wall clock time the game clock last caught up to
+    pub gravity_at: Duration, // Game time of the last gravity step
     pub tick_rate: Duration,
+    pub lock_started: Option<Duration>, // Set while the current shape rests on the stack
+    pub lock_resets: u32,
+    pub lowest_row: isize,
     pub shape_generator: ShapeGenerator,
+    pub garbage: GarbageGenerator,
+    pub garbage_cleared: u32,
+    pub ruleset: Ruleset,
+    pub seed: u64,
+    pub input_log: Vec<(Duration, Input)>, // Inputs that changed the game and their times, for replays
 }
 
 impl Game {
-    pub fn new() -> Self {
-        let shapes = vec![
-            Shape::new(&[&[0, 1, 1], &[1, 1, 0], &[0, 0, 0]], Color::Green),
-            Shape::new(&[&[1, 1, 0], &[0, 1, 1], &[0, 0, 0]], Color::Red),
-            Shape::new(&[&[0, 1, 0], &[1, 1, 1], &[0, 0, 0]], Color::Yellow),
-            Shape::new(&[&[0, 0, 1], &[1, 1, 1], &[0, 0, 0]], Color::Blue),
-            Shape::new(&[&[1, 0, 0], &[1, 1, 1], &[0, 0, 0]], Color::Magenta),
-            Shape::new(&[&[1, 1], &[1, 1]], Color::Cyan),
-            Shape::new(
-                &[&[0, 0, 0, 0], &[1, 1, 1, 1], &[0, 0, 0, 0], &[0, 0, 0, 0]],
-                Color::White,
-            ),
-        ];
-
-        let mut shape_generator = ShapeGenerator::new(shapes);
-        let permuted_shapes = shape_generator.generate_permutation();
+    /// Start a game whose pieces and garbage are all decided by `seed`, so two
+    /// games with the same seed and ruleset deal the same sequence
+    pub fn new(board: BoardSize, mode: GameMode, ruleset: Ruleset, seed: u64) -> Self {
+        let mut shape_generator = ShapeGenerator::new(tetrominoes(), ruleset.randomizer, seed);
+        let current = Self::spawn_shape(board, shape_generator.next_shape());
 
-        Self {
-            table: [[0; COLS]; ROWS],
-            current: permuted_shapes[0].clone(),
-            next_shape: permuted_shapes[1].clone(),
+        let mut game = Self {
+            board,
+            table: vec![vec![Cell::Empty; board.width]; board.rows()],
+            lowest_row: current.row,
+            current,
+            next_queue: shape_generator.peek(NEXT_QUEUE_SIZE),
+            hold: None,
+            hold_used: false,
             score: 0,
-            running: true,
+            level: 1,
+            lines: 0,
+            stats: Stats::default(),
+            last_action: Action::Spawn,
+            last_clear: None,
+            combo: None,
+            back_to_back: false,
+            state: GameState::Playing,
+            mode,
             paused: false,
-            last_tick: Instant::now(),
-            tick_rate: Duration::from_millis(500),
+            time: Duration::ZERO,
+            last_update: Instant::now(),
+            gravity_at: Duration::ZERO,
+            tick_rate: ruleset.gravity.tick_rate(1),
+            lock_started: None,
+            lock_resets: 0,
             shape_generator,
-        }
+            garbage: GarbageGenerator::new(seed, ruleset.messiness),
+            garbage_cleared: 0,
+            ruleset,
+            seed,
+            input_log: Vec::new(),
+        };
+        game.refill_garbage();
+        game.enter_current_shape();
+        game
     }
 
+    /// Whether the game has ended, either lost or with the mode's goal reached
     pub fn is_game_over(&self) -> bool {
-        let height = self.current.array.len();
-        let width = self.current.array[0].len();
+        self.state != GameState::Playing
+    }
 
-        for x in 0..width {
-            for y in 0..height {
-                if self.current.array[x][y] == 1 {
-                    let new_row = (self.current.row + x as isize) as usize;
-                    let new_col = (self.current.col + y as isize) as usize;
+    pub fn game_over_reason(&self) -> Option<GameOverReason> {
+        match self.state {
+            GameState::Over(reason) => Some(reason),
+            GameState::Playing | GameState::Complete => None,
+        }
+    }
 
-                    if new_row >= ROWS || new_col >= COLS || self.table[new_row][new_col] == 1 {
-                        return true;
-                    }
-                }
+    /// End the game, keeping the first reason if it is already over. Modes that
+    /// don't top out clear the stack away instead
+    fn end_game(&mut self, reason: GameOverReason) {
+        if !self.mode.tops_out() {
+            for row in self.table.iter_mut() {
+                row.fill(Cell::Empty);
             }
+            return;
+        }
+
+        if self.state == GameState::Playing {
+            self.state = GameState::Over(reason);
+        }
+    }
+
+    /// Pause or resume the game. Time spent paused doesn't count as play time
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused && !paused {
+            self.last_update = Instant::now();
         }
-        false
+        self.paused = paused;
+    }
+
+    /// Finish the game once the mode's goal is reached
+    fn check_goal(&mut self) {
+        if self.state == GameState::Playing && self.mode.is_complete(self) {
+            self.state = GameState::Complete;
+        }
+    }
+
+    /// Return whether a board cell is blocked. Cells outside the walls and floor
+    /// count as blocked
+    pub fn is_filled(&self, row: isize, col: isize) -> bool {
+        row < 0
+            || col < 0
+            || row >= self.board.rows() as isize
+            || col >= self.board.width as isize
+            || self.table[row as usize][col as usize].is_filled()
     }
 
     pub fn is_valid_position(&self, shape: &Shape) -> bool {
@@ -78,13 +308,7 @@ impl Game {
                     let row = row_start + y as isize;
                     let col = col_start + x as isize;
 
-                    if row < 0 || col < 0 || col >= COLS as isize || row >= ROWS as isize {
-                        return false;
-                    }
-
-                    let row_usize = row as usize;
-                    let col_usize = col as usize;
-                    if self.table[row_usize][col_usize] == 1 {
+                    if self.is_filled(row, col) {
                         return false;
                     }
                 }
@@ -93,27 +317,240 @@ impl Game {
         })
     }
 
-    pub fn move_shape(&mut self, dx: isize, dy: isize) {
+    /// Move the current shape, returning whether that changed anything. A
+    /// shape that can't move down starts its lock delay instead
+    fn move_shape(&mut self, dx: isize, dy: isize) -> bool {
         let mut temp = self.current.clone();
         temp.row += dy;
         temp.col += dx;
 
-        if temp.row >= 0 && self.is_valid_position(&temp) {
+        if self.is_valid_position(&temp) {
             self.current = temp;
-        } else if dy > 0 {
-            self.place_shape();
+            self.last_action = Action::Move;
+            self.sink();
+            self.update_lock_delay();
+            true
+        } else if dy > 0 && self.lock_started.is_none() {
+            // The shape has landed, start the lock delay instead of locking at once
+            self.lock_started = Some(self.time);
+            true
+        } else {
+            false
         }
     }
 
-    pub fn rotate_shape(&mut self) {
-        let mut temp = self.current.clone();
-        temp.rotate();
-        if self.is_valid_position(&temp) {
-            self.current = temp;
+    /// Get ready for a player input. Anything already due, like gravity sped up
+    /// by the previous input's level up, happens first so it doesn't matter
+    /// whether a tick came in between. Returns `false` while paused or once the
+    /// game is over, inputs do nothing then
+    fn accepts_input(&mut self) -> bool {
+        self.advance(self.time);
+        !self.paused && !self.is_game_over()
+    }
+
+    /// Note an input that changed the game at the current game time so the game
+    /// can be replayed. Inputs that did nothing, like a shift into the wall,
+    /// are left out: replaying them wouldn't change anything either
+    fn record(&mut self, input: Input) {
+        self.input_log.push((self.time, input));
+    }
+
+    /// Apply a recorded input, the way the player's keys did
+    pub fn apply(&mut self, input: Input) {
+        match input {
+            Input::MoveLeft => self.shift(-1),
+            Input::MoveRight => self.shift(1),
+            Input::SoftDrop => self.soft_drop(),
+            Input::HardDrop => self.hard_drop(),
+            Input::RotateClockwise => self.rotate_shape(Rotation::Clockwise),
+            Input::RotateCounterClockwise => self.rotate_shape(Rotation::CounterClockwise),
+            Input::Rotate180 => self.rotate_shape(Rotation::Half),
+            Input::Hold => self.hold_shape(),
+        }
+    }
+
+    /// Move the current shape one column left (`-1`) or right (`1`)
+    pub fn shift(&mut self, dx: isize) {
+        let input = if dx < 0 {
+            Input::MoveLeft
+        } else {
+            Input::MoveRight
+        };
+        if self.accepts_input() && self.move_shape(dx, 0) {
+            self.record(input);
+        }
+    }
+
+    /// Move the current shape down one row, awarding 1 point if it moved
+    pub fn soft_drop(&mut self) {
+        if !self.accepts_input() {
+            return;
+        }
+        let row = self.current.row;
+        if self.move_shape(0, 1) {
+            self.record(Input::SoftDrop);
+        }
+        if self.current.row > row {
+            self.score += 1;
+        }
+    }
+
+    /// Catch the game clock up with the wall clock. The clock only moves in whole
+    /// milliseconds so recorded inputs land on exactly the same game time in a replay
+    pub fn tick(&mut self) {
+        let elapsed = Instant::now().duration_since(self.last_update);
+        let elapsed = Duration::from_millis(elapsed.as_millis() as u64);
+        self.last_update += elapsed;
+        self.advance(self.time + elapsed);
+    }
+
+    /// Run the game clock forward to `to`, applying every gravity step and lock
+    /// delay expiry at the exact game time it falls due. The result only depends
+    /// on the inputs and the times they were applied at, not on how often this
+    /// is called
+    pub fn advance(&mut self, to: Duration) {
+        while !self.is_game_over() {
+            let gravity_due = self
+                .mode
+                .has_gravity()
+                .then(|| self.gravity_at + self.tick_rate);
+            let lock_due = self
+                .lock_started
+                .map(|started| started + self.ruleset.lock_delay());
+            let Some(next) = [gravity_due, lock_due, self.mode.time_limit()]
+                .into_iter()
+                .flatten()
+                .filter(|&due| due <= to)
+                .min()
+            else {
+                break;
+            };
+
+            self.time = self.time.max(next);
+            if gravity_due == Some(next) {
+                self.gravity_at = next;
+                self.move_shape(0, 1); // Move the shape down over time
+            } else if lock_due == Some(next) {
+                self.place_shape();
+            }
+            self.check_goal();
+        }
+
+        // The clock stops with the game so the results show the final time
+        if !self.is_game_over() {
+            self.time = self.time.max(to);
+            self.check_goal();
+        }
+    }
+
+    fn is_grounded(&self) -> bool {
+        let mut below = self.current.clone();
+        below.row += 1;
+        !self.is_valid_position(&below)
+    }
+
+    /// Restart the lock delay after a successful move or rotation. Each restart
+    /// while grounded counts against the ruleset's `max_lock_resets`; reaching a new lowest row
+    /// gives the full allowance back
+    fn update_lock_delay(&mut self) {
+        if self.current.row > self.lowest_row {
+            self.lowest_row = self.current.row;
+            self.lock_resets = 0;
+        }
+
+        if self.lock_started.take().is_some() {
+            self.lock_resets += 1;
+        }
+
+        if self.is_grounded() {
+            if self.lock_resets > self.ruleset.max_lock_resets {
+                self.place_shape();
+            } else {
+                self.lock_started = Some(self.time);
+            }
+        }
+    }
+
+    fn reset_lock_delay(&mut self) {
+        self.last_action = Action::Spawn;
+        self.lock_started = None;
+        self.lock_resets = 0;
+        self.lowest_row = self.current.row;
+    }
+
+    /// Return the current shape moved down to the lowest row it can reach
+    pub fn ghost_shape(&self) -> Shape {
+        let mut ghost = self.current.clone();
+        loop {
+            ghost.row += 1;
+            if !self.is_valid_position(&ghost) {
+                ghost.row -= 1;
+                return ghost;
+            }
+        }
+    }
+
+    /// Drop the current shape straight to its landing row and lock it at once,
+    /// awarding 2 points per cell dropped
+    pub fn hard_drop(&mut self) {
+        if !self.accepts_input() {
+            return;
+        }
+        self.record(Input::HardDrop);
+        let ghost = self.ghost_shape();
+        let distance = (ghost.row - self.current.row) as u32;
+
+        self.current = ghost;
+        self.score += 2 * distance;
+        if distance > 0 {
+            self.last_action = Action::Move;
+        }
+        self.place_shape();
+    }
+
+    /// Rotate the current shape, trying each SRS wall kick in turn
+    /// and keeping the first one that lands on a free position
+    pub fn rotate_shape(&mut self, rotation: Rotation) {
+        let input = match rotation {
+            Rotation::Clockwise => Input::RotateClockwise,
+            Rotation::CounterClockwise => Input::RotateCounterClockwise,
+            Rotation::Half => Input::Rotate180,
+        };
+        if !self.accepts_input() {
+            return;
+        }
+        let mut rotated = self.current.clone();
+        rotated.rotate(rotation);
+
+        let kicks = srs::kicks(
+            self.current.kind,
+            self.current.orientation,
+            rotated.orientation,
+        );
+        for (kick, &(dx, dy)) in kicks.iter().enumerate() {
+            let mut temp = rotated.clone();
+            temp.col += dx;
+            temp.row -= dy; // SRS offsets point up, board rows grow downwards
+            if self.is_valid_position(&temp) {
+                self.record(input);
+                self.current = temp;
+                self.last_action = Action::Rotate { rotation, kick };
+                self.sink();
+                self.update_lock_delay();
+                return;
+            }
         }
     }
 
     fn place_shape(&mut self) {
+        let tspin = self.detect_tspin();
+
+        // Lock out: the shape locked without any of its cells reaching the visible field
+        let buffer = self.board.buffer as isize;
+        let locked_out = self.current.array.iter().enumerate().all(|(i, row)| {
+            row.iter().all(|&cell| cell == 0) || self.current.row + (i as isize) < buffer
+        });
+
         for i in 0..self.current.width {
             for j in 0..self.current.width {
                 if self.current.array[i][j] == 1 {
@@ -121,42 +558,608 @@ impl Game {
                     let col = self.current.col + j as isize;
 
                     if row >= 0 && col >= 0 {
-                        self.table[row as usize][col as usize] = 1;
+                        self.table[row as usize][col as usize] = Cell::Filled(self.current.kind);
                     }
                 }
             }
         }
 
-        self.check_lines();
+        let cleared = self.check_lines();
+        self.score_lines(cleared, tspin);
+        self.hold_used = false;
 
-        self.current = self.next_shape.clone();
-        if let Some(next) = self.shape_generator.next_shape() {
-            self.next_shape = next;
-        } else {
-            self.shape_generator.generate_permutation();
-            if let Some(next) = self.shape_generator.next_shape() {
-                self.next_shape = next;
-            }
+        // The clock stops on the lock that reaches the goal, not on the next tick
+        self.check_goal();
+        if locked_out {
+            self.end_game(GameOverReason::LockOut);
+        } else if self.table[0].iter().any(|cell| cell.is_filled()) {
+            self.end_game(GameOverReason::TopOut);
+        }
+        if self.is_game_over() {
+            return;
+        }
+
+        // Garbage rises once the next shape is out, so a lift only ever moves
+        // the shape that is about to fall
+        self.spawn_next_shape();
+        self.refill_garbage();
+        if self.is_game_over() {
+            return;
         }
+        self.enter_current_shape();
+    }
+
+    /// Swap the current shape with the held one, or stash it and take the next
+    /// shape when the hold slot is empty
+    pub fn hold_shape(&mut self) {
+        if !self.accepts_input() || self.hold_used {
+            return;
+        }
+        self.record(Input::Hold);
+
+        let mut held = self.current.clone();
+        held.reset();
 
+        match self.hold.replace(held) {
+            Some(previous) => self.current = Self::spawn_shape(self.board, previous),
+            None => self.spawn_next_shape(),
+        }
+        self.hold_used = true;
+        self.enter_current_shape();
+    }
+
+    /// Place a shape in the two hidden rows just above the visible field (rows 21
+    /// and 22 on a standard board), centered and rounded to the left
+    fn spawn_shape(board: BoardSize, mut shape: Shape) -> Shape {
+        shape.row = (board.buffer as isize - 2).max(0);
+        shape.col = (board.width as isize - shape.width as isize) / 2;
+        shape
+    }
+
+    /// Check a freshly spawned shape for a block out, then drop it one row
+    /// straight away if nothing is in the way, as the guideline asks
+    fn enter_current_shape(&mut self) {
         if !self.is_valid_position(&self.current) {
-            self.running = false;
+            self.end_game(GameOverReason::BlockOut);
+            if self.is_game_over() {
+                return;
+            }
+        }
+
+        let mut below = self.current.clone();
+        below.row += 1;
+        if self.is_valid_position(&below) {
+            self.current = below;
         }
+        self.sink();
+        self.reset_lock_delay();
     }
 
-    fn check_lines(&mut self) {
-        let mut new_table = [[0; COLS]; ROWS];
-        let mut new_row = ROWS as isize - 1;
+    /// At 20G a shape never spends any time in the air: it drops to its landing
+    /// row as it spawns and after every move, before the next input can act on it
+    fn sink(&mut self) {
+        if self.ruleset.gravity != Gravity::TwentyG || !self.mode.has_gravity() {
+            return;
+        }
+        let ghost = self.ghost_shape();
+        if ghost.row > self.current.row {
+            self.current = ghost;
+            self.last_action = Action::Move;
+        }
+    }
+
+    fn spawn_next_shape(&mut self) {
+        self.current = Self::spawn_shape(self.board, self.shape_generator.next_shape());
+        self.next_queue = self.shape_generator.peek(NEXT_QUEUE_SIZE);
+    }
+
+    /// Classify a T piece lock with the 3-corner test. At least three of the
+    /// corners around the T's center must be filled (walls and floor count) and
+    /// the last action must be a rotation. It is a Mini unless both corners the
+    /// T points at are filled or a quarter turn used the last SRS kick. The 180
+    /// degree tables have no such kick, their fifth entry is an ordinary shift
+    fn detect_tspin(&self) -> TSpin {
+        let (rotation, kick) = match self.last_action {
+            Action::Rotate { rotation, kick } if self.current.kind == PieceKind::T => {
+                (rotation, kick)
+            }
+            _ => return TSpin::None,
+        };
+
+        let center_row = self.current.row + 1;
+        let center_col = self.current.col + 1;
+        let filled = |dr: isize, dc: isize| self.is_filled(center_row + dr, center_col + dc);
+
+        let top_left = filled(-1, -1);
+        let top_right = filled(-1, 1);
+        let bottom_left = filled(1, -1);
+        let bottom_right = filled(1, 1);
 
-        for i in (0..ROWS).rev() {
-            if self.table[i].iter().all(|&x| x == 1) {
-                self.score += 100;
+        let corners = [top_left, top_right, bottom_left, bottom_right]
+            .iter()
+            .filter(|&&corner| corner)
+            .count();
+        if corners < 3 {
+            return TSpin::None;
+        }
+
+        let front = match self.current.orientation {
+            Orientation::Spawn => [top_left, top_right],
+            Orientation::Right => [top_right, bottom_right],
+            Orientation::Two => [bottom_left, bottom_right],
+            Orientation::Left => [top_left, bottom_left],
+        };
+
+        let last_kick = rotation != Rotation::Half && kick == 4;
+        if front.iter().all(|&corner| corner) || last_kick {
+            TSpin::Full
+        } else {
+            TSpin::Mini
+        }
+    }
+
+    /// Remove full rows and return how many were cleared
+    fn check_lines(&mut self) -> usize {
+        let rows = self.board.rows();
+        let mut new_table = vec![vec![Cell::Empty; self.board.width]; rows];
+        let mut new_row = rows as isize - 1;
+
+        let mut cleared = 0;
+
+        for i in (0..rows).rev() {
+            if self.table[i].iter().all(|cell| cell.is_filled()) {
+                cleared += 1;
+                if self.table[i].contains(&Cell::Garbage) {
+                    self.garbage_cleared += 1;
+                }
             } else if new_row >= 0 {
-                new_table[new_row as usize] = self.table[i];
+                new_table[new_row as usize] = self.table[i].clone();
                 new_row -= 1;
             }
         }
 
         self.table = new_table;
+        cleared
+    }
+
+    /// Award guideline points for a lock and advance the level every 10 lines
+    fn score_lines(&mut self, cleared: usize, tspin: TSpin) {
+        let mut points = scoring::clear_points(cleared, tspin);
+        let mut back_to_back = false;
+
+        if cleared > 0 {
+            let difficult = scoring::is_difficult(cleared, tspin);
+            if difficult && self.back_to_back {
+                points = scoring::back_to_back_points(points);
+                back_to_back = true;
+            }
+            self.back_to_back = difficult;
+
+            let combo = self.combo.map_or(0, |combo| combo + 1);
+            points += scoring::combo_points(combo);
+            self.combo = Some(combo);
+        } else {
+            self.combo = None;
+        }
+
+        self.stats
+            .record_lock(cleared, tspin, self.combo, back_to_back);
+        self.score += points * self.level;
+        if cleared > 0 || tspin != TSpin::None {
+            self.last_clear = Some(ClearEvent {
+                lines: cleared,
+                tspin,
+                combo: self.combo.unwrap_or(0),
+                back_to_back,
+                at: self.time,
+            });
+        }
+
+        self.lines += cleared as u32;
+        self.level = self.lines / LINES_PER_LEVEL + 1;
+        self.tick_rate = self.ruleset.gravity.tick_rate(self.level);
+    }
+
+    /// Push `count` garbage rows up from the bottom of the board. The stack tops
+    /// out if that lifts anything off the top of the buffer
+    pub fn add_garbage(&mut self, count: usize) {
+        let mut overflowed = false;
+        for _ in 0..count {
+            let top = self.table.remove(0);
+            overflowed |= top.iter().any(|cell| cell.is_filled());
+            self.table.push(self.garbage.next_row(self.board.width));
+        }
+
+        // Lift the falling shape out of the new rows
+        while !self.is_valid_position(&self.current) && self.current.row > 0 {
+            self.current.row -= 1;
+        }
+        self.lowest_row = self.lowest_row.min(self.current.row);
+
+        if overflowed {
+            self.end_game(GameOverReason::TopOut);
+        }
+    }
+
+    /// Keep the board stocked with garbage in modes that dig through it, up to
+    /// the mode's visible rows and never more than is left to clear
+    fn refill_garbage(&mut self) {
+        let Some(goal) = self.mode.garbage_goal() else {
+            return;
+        };
+
+        let on_board = self
+            .table
+            .iter()
+            .filter(|row| row.contains(&Cell::Garbage))
+            .count() as u32;
+        let left_to_add = goal.saturating_sub(self.garbage_cleared + on_board);
+        let room = (DIG_GARBAGE_ROWS as u32).saturating_sub(on_board);
+        self.add_garbage(left_to_add.min(room) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::shape::tests::assert_complete_bags;
+
+    #[test]
+    fn same_seed_deals_the_same_shapes() {
+        for (randomizer, seed) in RandomizerKind::ALL.into_iter().zip(100..) {
+            let ruleset = Ruleset {
+                randomizer,
+                ..Ruleset::default()
+            };
+            let mut first = Game::new(BoardSize::default(), GameMode::Zen, ruleset, seed);
+            let mut second = Game::new(BoardSize::default(), GameMode::Zen, ruleset, seed);
+
+            let first_kinds: Vec<PieceKind> = first
+                .shape_generator
+                .peek(100)
+                .iter()
+                .map(|shape| shape.kind)
+                .collect();
+            let second_kinds: Vec<PieceKind> = second
+                .shape_generator
+                .peek(100)
+                .iter()
+                .map(|shape| shape.kind)
+                .collect();
+
+            assert_eq!(first.current.kind, second.current.kind);
+            assert_eq!(first_kinds, second_kinds, "{} diverged", randomizer.name());
+        }
+    }
+
+    #[test]
+    fn first_bag_is_dealt_once() {
+        for seed in 0..100 {
+            let game = Game::new(
+                BoardSize::default(),
+                GameMode::Marathon,
+                Ruleset::default(),
+                seed,
+            );
+            let mut first_bag = vec![game.current.kind];
+            first_bag.extend(game.next_queue.iter().map(|shape| shape.kind));
+
+            assert_complete_bags(&first_bag);
+        }
+    }
+
+    #[test]
+    fn spawned_shapes_follow_the_bags() {
+        let mut game = Game::new(
+            BoardSize::default(),
+            GameMode::Marathon,
+            Ruleset::default(),
+            7,
+        );
+        let mut spawned = vec![game.current.kind];
+
+        for _ in 0..7 * 300 {
+            let expected = game.next_queue[0].kind;
+            game.hard_drop();
+            assert_eq!(game.current.kind, expected);
+            spawned.push(game.current.kind);
+
+            // Empty the board so the stack never tops out
+            for row in game.table.iter_mut() {
+                row.fill(Cell::Empty);
+            }
+        }
+
+        assert_complete_bags(&spawned[..7 * 300]);
+    }
+
+    #[test]
+    fn pausing_on_the_ground_keeps_the_lock_delay() {
+        let mut game = Game::new(
+            BoardSize::default(),
+            GameMode::Marathon,
+            Ruleset::default(),
+            0,
+        );
+        while game.lock_started.is_none() {
+            game.advance(game.time + Duration::from_millis(100));
+        }
+        let landed = game.time;
+
+        // A long pause must not count towards the lock delay
+        game.set_paused(true);
+        game.last_update -= Duration::from_secs(10);
+        game.set_paused(false);
+        game.tick();
+        assert_eq!(game.stats.pieces, 0);
+
+        game.advance(landed + game.ruleset.lock_delay());
+        assert_eq!(game.stats.pieces, 1);
+    }
+
+    #[test]
+    fn twenty_g_shapes_never_leave_the_ground() {
+        let ruleset = Ruleset {
+            gravity: Gravity::TwentyG,
+            ..Ruleset::default()
+        };
+        let mut game = Game::new(BoardSize::default(), GameMode::Marathon, ruleset, 3);
+        assert_eq!(game.current.row, game.ghost_shape().row);
+
+        // A tower under the spawn position, the shape falls as it steps off it
+        for row in game.table.iter_mut().skip(30) {
+            row[3..7].fill(Cell::Garbage);
+        }
+        game.hard_drop();
+        assert!(game.current.row < 30);
+        for _ in 0..game.board.width {
+            game.shift(-1);
+            assert_eq!(game.current.row, game.ghost_shape().row);
+        }
+        game.rotate_shape(Rotation::Clockwise);
+        assert_eq!(game.current.row, game.ghost_shape().row);
+
+        game.hard_drop();
+        assert_eq!(game.current.row, game.ghost_shape().row);
+    }
+
+    fn marathon() -> Game {
+        Game::new(
+            BoardSize::default(),
+            GameMode::Marathon,
+            Ruleset::default(),
+            0,
+        )
+    }
+
+    #[test]
+    fn spawning_into_the_stack_is_a_block_out() {
+        let mut game = marathon();
+        for row in &mut game.table[18..22] {
+            row[..9].fill(Cell::Garbage);
+        }
+        game.spawn_next_shape();
+        game.enter_current_shape();
+        assert_eq!(game.state, GameState::Over(GameOverReason::BlockOut));
+    }
+
+    #[test]
+    fn locking_inside_the_buffer_is_a_lock_out() {
+        let mut game = marathon();
+        game.current.row = 5;
+        game.place_shape();
+        assert_eq!(game.state, GameState::Over(GameOverReason::LockOut));
+    }
+
+    #[test]
+    fn garbage_pushing_the_stack_off_the_top_is_a_top_out() {
+        let mut game = marathon();
+        game.table[0][0] = Cell::Garbage;
+        game.add_garbage(1);
+        assert_eq!(game.state, GameState::Over(GameOverReason::TopOut));
+    }
+
+    #[test]
+    fn the_first_reason_is_kept() {
+        let mut game = marathon();
+        game.end_game(GameOverReason::LockOut);
+        game.end_game(GameOverReason::TopOut);
+        assert_eq!(game.game_over_reason(), Some(GameOverReason::LockOut));
+    }
+
+    #[test]
+    fn zen_clears_the_board_instead_of_ending() {
+        let mut game = Game::new(BoardSize::default(), GameMode::Zen, Ruleset::default(), 0);
+        game.table[0][0] = Cell::Garbage;
+        game.table[39][5] = Cell::Garbage;
+        game.end_game(GameOverReason::TopOut);
+
+        assert_eq!(game.state, GameState::Playing);
+        assert!(game.table.iter().flatten().all(|&cell| cell == Cell::Empty));
+    }
+
+    /// Land the first shape of a Zen game, which has no gravity to move it
+    fn grounded(ruleset: Ruleset) -> Game {
+        let mut game = Game::new(BoardSize::default(), GameMode::Zen, ruleset, 0);
+        while game.lock_started.is_none() {
+            game.soft_drop();
+        }
+        game
+    }
+
+    #[test]
+    fn the_ruleset_sets_the_lock_delay() {
+        let ruleset = Ruleset {
+            lock_delay_ms: 200,
+            ..Ruleset::default()
+        };
+        let mut game = grounded(ruleset);
+        let landed = game.lock_started.unwrap();
+
+        game.advance(landed + Duration::from_millis(199));
+        assert_eq!(game.stats.pieces, 0);
+        game.advance(landed + Duration::from_millis(200));
+        assert_eq!(game.stats.pieces, 1);
+    }
+
+    #[test]
+    fn the_ruleset_caps_lock_resets() {
+        let ruleset = Ruleset {
+            max_lock_resets: 3,
+            ..Ruleset::default()
+        };
+        let mut game = grounded(ruleset);
+
+        // Each shuffle on the ground restarts the delay until the cap runs out
+        for dx in [1, -1, 1] {
+            game.shift(dx);
+            assert_eq!(game.stats.pieces, 0);
+        }
+        game.shift(-1);
+        assert_eq!(game.stats.pieces, 1);
+    }
+
+    #[test]
+    fn paused_games_ignore_inputs() {
+        let mut game = Game::new(
+            BoardSize::default(),
+            GameMode::Sprint,
+            Ruleset::default(),
+            0,
+        );
+        let (row, col) = (game.current.row, game.current.col);
+
+        game.set_paused(true);
+        game.shift(1);
+        game.rotate_shape(Rotation::Clockwise);
+        game.soft_drop();
+        game.hold_shape();
+        game.hard_drop();
+
+        assert_eq!((game.current.row, game.current.col), (row, col));
+        assert_eq!(game.current.orientation, Orientation::Spawn);
+        assert!(game.hold.is_none());
+        assert_eq!(game.stats.pieces, 0);
+        assert!(game.input_log.is_empty());
+    }
+
+    #[test]
+    fn inputs_that_change_nothing_are_not_logged() {
+        let mut game = Game::new(BoardSize::default(), GameMode::Zen, Ruleset::default(), 0);
+        for _ in 0..100 {
+            game.shift(-1);
+        }
+        let moves = game.input_log.len();
+        assert!(moves < 10, "{moves} shifts logged");
+
+        game.hold_shape();
+        game.hold_shape();
+        assert_eq!(game.input_log.len(), moves + 1);
+
+        // Soft drops log until the one that starts the lock delay
+        while game.lock_started.is_none() {
+            game.soft_drop();
+        }
+        let logged = game.input_log.len();
+        game.soft_drop();
+        assert_eq!(game.input_log.len(), logged);
+    }
+
+    /// A T facing up with both corners below it and the top left corner filled,
+    /// so only the last SRS kick can make it a full T-spin
+    fn tspin_setup(last_action: Action) -> Game {
+        let mut game = Game::new(BoardSize::default(), GameMode::Zen, Ruleset::default(), 0);
+        let mut t = tetrominoes()
+            .into_iter()
+            .find(|shape| shape.kind == PieceKind::T)
+            .unwrap();
+        t.row = 30;
+        t.col = 3;
+        game.current = t;
+        game.table[30][3] = Cell::Garbage;
+        game.table[32][3] = Cell::Garbage;
+        game.table[32][5] = Cell::Garbage;
+        game.last_action = last_action;
+        game
+    }
+
+    #[test]
+    fn detects_tspins_with_three_corners() {
+        let rotate = |rotation, kick| Action::Rotate { rotation, kick };
+
+        assert_eq!(tspin_setup(Action::Move).detect_tspin(), TSpin::None);
+        assert_eq!(
+            tspin_setup(rotate(Rotation::Clockwise, 0)).detect_tspin(),
+            TSpin::Mini
+        );
+
+        let mut game = tspin_setup(rotate(Rotation::Clockwise, 0));
+        game.table[30][5] = Cell::Garbage;
+        assert_eq!(game.detect_tspin(), TSpin::Full);
+
+        let mut game = tspin_setup(rotate(Rotation::Clockwise, 0));
+        game.table[32][3] = Cell::Empty;
+        assert_eq!(game.detect_tspin(), TSpin::None);
+    }
+
+    #[test]
+    fn only_the_last_quarter_turn_kick_upgrades_a_mini() {
+        let rotate = |rotation, kick| Action::Rotate { rotation, kick };
+
+        assert_eq!(
+            tspin_setup(rotate(Rotation::Clockwise, 4)).detect_tspin(),
+            TSpin::Full
+        );
+        assert_eq!(
+            tspin_setup(rotate(Rotation::CounterClockwise, 4)).detect_tspin(),
+            TSpin::Full
+        );
+        assert_eq!(
+            tspin_setup(rotate(Rotation::Half, 4)).detect_tspin(),
+            TSpin::Mini
+        );
+    }
+
+    #[test]
+    fn garbage_refills_leave_the_next_spawn_alone() {
+        let board = BoardSize::default();
+        let mut dig = Game::new(board, GameMode::Dig(18), Ruleset::default(), 5);
+        let mut zen = Game::new(board, GameMode::Zen, Ruleset::default(), 5);
+
+        // Clear the bottom garbage row so the lock is followed by a refill
+        let bottom = dig.table.len() - 1;
+        dig.table[bottom].fill(Cell::Garbage);
+        dig.hard_drop();
+        zen.hard_drop();
+        assert_eq!(dig.garbage_cleared, 1);
+
+        assert_eq!(dig.current.kind, zen.current.kind);
+        assert_eq!(dig.current.row, zen.current.row);
+        assert_eq!(dig.current.col, zen.current.col);
+        assert_eq!(dig.lowest_row, dig.current.row);
+        assert_eq!(dig.lock_started, None);
+    }
+
+    #[test]
+    fn rising_garbage_lifts_the_next_shape() {
+        let mut dig = Game::new(
+            BoardSize::default(),
+            GameMode::Dig(18),
+            Ruleset::default(),
+            5,
+        );
+        for row in dig.table.iter_mut() {
+            row.fill(Cell::Empty);
+        }
+        // A tower the refill pushes up through the spawn position
+        for row in 22..dig.table.len() {
+            dig.table[row][4] = Cell::Filled(PieceKind::O);
+        }
+        dig.hard_drop();
+
+        assert_eq!(dig.state, GameState::Playing);
+        assert!(dig.is_valid_position(&dig.current));
+        assert!(dig.current.row < 12);
+        assert_eq!(dig.lowest_row, dig.current.row);
     }
 }
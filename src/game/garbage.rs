@@ -0,0 +1,90 @@
+use crate::game::control::Cell;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Mixed into the game seed so garbage holes don't follow the piece sequence
+const GARBAGE_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Builds garbage rows: full rows with a single hole. `messiness` is the chance,
+/// in percent, that a row's hole moves away from the column of the row before it
+pub struct GarbageGenerator {
+    rng: ChaCha8Rng,
+    messiness: u32,
+    hole: Option<usize>,
+}
+
+impl GarbageGenerator {
+    pub fn new(seed: u64, messiness: u32) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed ^ GARBAGE_SEED_SALT),
+            messiness: messiness.min(100),
+            hole: None,
+        }
+    }
+
+    pub fn next_row(&mut self, width: usize) -> Vec<Cell> {
+        let hole = match self.hole {
+            Some(previous) if width < 2 || self.rng.random_range(0..100) >= self.messiness => {
+                previous.min(width.saturating_sub(1))
+            }
+            // Moving the hole always picks a different column than before
+            Some(previous) => (previous + self.rng.random_range(1..width)) % width,
+            None => self.rng.random_range(0..width.max(1)),
+        };
+        self.hole = Some(hole);
+
+        let mut row = vec![Cell::Garbage; width];
+        if let Some(cell) = row.get_mut(hole) {
+            *cell = Cell::Empty;
+        }
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hole(row: &[Cell]) -> usize {
+        let holes: Vec<usize> = (0..row.len())
+            .filter(|&col| row[col] == Cell::Empty)
+            .collect();
+        assert_eq!(holes.len(), 1, "row {row:?} should have one hole");
+        holes[0]
+    }
+
+    #[test]
+    fn clean_garbage_keeps_one_column() {
+        for seed in 0..20 {
+            let mut garbage = GarbageGenerator::new(seed, 0);
+            let first = hole(&garbage.next_row(10));
+            for _ in 0..100 {
+                assert_eq!(hole(&garbage.next_row(10)), first, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn messy_garbage_always_moves_the_hole() {
+        for seed in 0..20 {
+            let mut garbage = GarbageGenerator::new(seed, 100);
+            let mut previous = hole(&garbage.next_row(10));
+            for _ in 0..100 {
+                let next = hole(&garbage.next_row(10));
+                assert_ne!(next, previous, "seed {seed}");
+                previous = next;
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_digs_the_same_holes() {
+        for seed in 0..20 {
+            let mut first = GarbageGenerator::new(seed, 50);
+            let mut second = GarbageGenerator::new(seed, 50);
+            for _ in 0..100 {
+                assert_eq!(first.next_row(10), second.next_row(10), "seed {seed}");
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Gravity of 20G drops a piece 20 rows every frame, i.e. straight to the floor
+const TWENTY_G_ROWS_PER_FRAME: f64 = 20.0;
+const FRAMES_PER_SECOND: f64 = 60.0;
+
+/// How fast pieces fall, selected per game mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gravity {
+    /// Guideline curve: `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per row
+    Guideline,
+    /// Constant speed of one row every 500 ms
+    Classic,
+    /// Pieces drop to the stack as they spawn and after every move, see
+    /// `Game::sink`. The tick rate is capped at 20 rows a frame
+    #[serde(rename = "20g")]
+    TwentyG,
+}
+
+impl Gravity {
+    pub const ALL: [Gravity; 3] = [Gravity::Guideline, Gravity::Classic, Gravity::TwentyG];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Gravity::Guideline => "guideline",
+            Gravity::Classic => "classic",
+            Gravity::TwentyG => "20g",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Gravity::ALL
+            .into_iter()
+            .find(|gravity| gravity.name() == name)
+    }
+
+    /// Return the time it takes a piece to fall one row at the given level. Values
+    /// below one frame mean several rows per frame, capped at 20G
+    pub fn tick_rate(self, level: u32) -> Duration {
+        let min_seconds = 1.0 / (FRAMES_PER_SECOND * TWENTY_G_ROWS_PER_FRAME);
+
+        let seconds = match self {
+            Gravity::Guideline => {
+                let level = level.saturating_sub(1) as f64;
+                (0.8 - level * 0.007).powf(level)
+            }
+            Gravity::Classic => 0.5,
+            Gravity::TwentyG => min_seconds,
+        };
+
+        Duration::from_secs_f64(seconds.max(min_seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(duration: Duration) -> f64 {
+        duration.as_secs_f64() * 1000.0
+    }
+
+    #[test]
+    fn guideline_gravity_follows_the_curve() {
+        assert_eq!(Gravity::Guideline.tick_rate(1), Duration::from_secs(1));
+        assert!((millis(Gravity::Guideline.tick_rate(2)) - 793.0).abs() < 0.001);
+        assert!((millis(Gravity::Guideline.tick_rate(10)) - 64.152).abs() < 0.001);
+
+        for level in 1..30 {
+            assert!(Gravity::Guideline.tick_rate(level + 1) <= Gravity::Guideline.tick_rate(level));
+        }
+    }
+
+    #[test]
+    fn gravity_is_capped_at_twenty_g() {
+        let twenty_g = Duration::from_secs_f64(1.0 / 1200.0);
+
+        assert_eq!(Gravity::TwentyG.tick_rate(1), twenty_g);
+        assert_eq!(Gravity::Guideline.tick_rate(30), twenty_g);
+        assert_eq!(Gravity::Classic.tick_rate(30), Duration::from_millis(500));
+    }
+}
@@ -1,4 +1,12 @@
 pub mod control;
+pub mod garbage;
+pub mod gravity;
+pub mod mode;
+pub mod randomizer;
+pub mod replay;
+pub mod scoring;
 pub mod shape;
+pub mod srs;
+pub mod stats;
 
 pub use control::Game;
@@ -0,0 +1,259 @@
+use crate::game::Game;
+use std::time::Duration;
+
+const MARATHON_LINES: u32 = 150;
+const SPRINT_LINES: u32 = 40;
+const ULTRA_TIME: Duration = Duration::from_secs(120);
+
+/// The goal of a game: what ends it, whether it is won and what the results show
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    /// Clear 150 lines while the level climbs
+    #[default]
+    Marathon,
+    /// Clear 40 lines as fast as possible
+    Sprint,
+    /// Score as much as possible in two minutes
+    Ultra,
+    /// Clear this many garbage lines as fast as possible
+    Dig(u32),
+    /// No gravity and no top out, play for as long as you like
+    Zen,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 7] = [
+        GameMode::Marathon,
+        GameMode::Sprint,
+        GameMode::Ultra,
+        GameMode::Dig(10),
+        GameMode::Dig(18),
+        GameMode::Dig(100),
+        GameMode::Zen,
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            GameMode::Marathon => "marathon".to_string(),
+            GameMode::Sprint => "sprint".to_string(),
+            GameMode::Ultra => "ultra".to_string(),
+            GameMode::Dig(lines) => format!("dig{}", lines),
+            GameMode::Zen => "zen".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        GameMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn title(self) -> String {
+        match self {
+            GameMode::Marathon => "Marathon".to_string(),
+            GameMode::Sprint => "Sprint 40L".to_string(),
+            GameMode::Ultra => "Ultra".to_string(),
+            GameMode::Dig(lines) => format!("Dig {}L", lines),
+            GameMode::Zen => "Zen".to_string(),
+        }
+    }
+
+    /// Lines to clear to finish the game
+    pub fn line_goal(self) -> Option<u32> {
+        match self {
+            GameMode::Marathon => Some(MARATHON_LINES),
+            GameMode::Sprint => Some(SPRINT_LINES),
+            GameMode::Ultra | GameMode::Dig(_) | GameMode::Zen => None,
+        }
+    }
+
+    /// Garbage lines to clear to finish the game
+    pub fn garbage_goal(self) -> Option<u32> {
+        match self {
+            GameMode::Dig(lines) => Some(lines),
+            _ => None,
+        }
+    }
+
+    /// Garbage lines still to clear, shown in the side panel while digging
+    pub fn garbage_left(self, game: &Game) -> Option<u32> {
+        self.garbage_goal()
+            .map(|goal| goal.saturating_sub(game.garbage_cleared))
+    }
+
+    /// Play time after which the game ends
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            GameMode::Ultra => Some(ULTRA_TIME),
+            _ => None,
+        }
+    }
+
+    pub fn has_gravity(self) -> bool {
+        self != GameMode::Zen
+    }
+
+    /// Whether a block out, lock out or top out ends the game. Otherwise the
+    /// stack is cleared away and play goes on
+    pub fn tops_out(self) -> bool {
+        self != GameMode::Zen
+    }
+
+    /// Whether the game has reached the mode's goal
+    pub fn is_complete(self, game: &Game) -> bool {
+        self.line_goal().is_some_and(|goal| game.lines >= goal)
+            || self
+                .garbage_goal()
+                .is_some_and(|goal| game.garbage_cleared >= goal)
+            || self.time_limit().is_some_and(|limit| game.time >= limit)
+    }
+
+    /// Whether results are ranked by the fastest time rather than the best score
+    pub fn ranks_by_time(self) -> bool {
+        matches!(self, GameMode::Sprint | GameMode::Dig(_))
+    }
+
+    /// Whether a finished game earns a place on the high score table. Races only
+    /// count once the goal is reached and Zen has no goal at all
+    pub fn is_ranked(self, game: &Game) -> bool {
+        match self {
+            GameMode::Sprint | GameMode::Dig(_) => self.is_complete(game),
+            GameMode::Marathon | GameMode::Ultra => true,
+            GameMode::Zen => false,
+        }
+    }
+
+    /// Progress towards the goal, shown next to the board while playing
+    pub fn status(self, game: &Game) -> Vec<String> {
+        match self {
+            GameMode::Marathon => vec![format!("Goal: {}/{}", game.lines, MARATHON_LINES)],
+            GameMode::Sprint => vec![
+                format!("Time: {}", format_time(game.time)),
+                format!("Left: {}", SPRINT_LINES.saturating_sub(game.lines)),
+            ],
+            GameMode::Ultra => vec![format!(
+                "Time left: {}",
+                format_time(ULTRA_TIME.saturating_sub(game.time))
+            )],
+            GameMode::Dig(_) => vec![format!("Time: {}", format_time(game.time))],
+            GameMode::Zen => Vec::new(),
+        }
+    }
+
+    /// Results shown once the game is over
+    pub fn summary(self, game: &Game) -> Vec<String> {
+        match self {
+            GameMode::Marathon => vec![
+                format!("Score: {}", game.score),
+                format!("Level: {}", game.level),
+                format!("Lines: {}/{}", game.lines, MARATHON_LINES),
+            ],
+            GameMode::Sprint | GameMode::Dig(_) if self.is_complete(game) => {
+                vec![format!("Time: {}", format_time(game.time))]
+            }
+            GameMode::Sprint => vec![
+                "Did not finish".to_string(),
+                format!("Lines: {}/{}", game.lines, SPRINT_LINES),
+            ],
+            GameMode::Dig(lines) => vec![
+                "Did not finish".to_string(),
+                format!("Garbage: {}/{}", game.garbage_cleared, lines),
+            ],
+            GameMode::Ultra | GameMode::Zen => vec![
+                format!("Score: {}", game.score),
+                format!("Lines: {}", game.lines),
+            ],
+        }
+    }
+}
+
+/// Format a duration as `m:ss.mmm`
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::control::{BoardSize, Cell, GameState, Ruleset};
+
+    fn game(mode: GameMode) -> Game {
+        Game::new(BoardSize::default(), mode, Ruleset::default(), 0)
+    }
+
+    #[test]
+    fn formats_time_to_the_millisecond() {
+        assert_eq!(format_time(Duration::from_millis(83_042)), "1:23.042");
+        assert_eq!(format_time(Duration::ZERO), "0:00.000");
+    }
+
+    #[test]
+    fn sprint_completes_at_forty_lines() {
+        let mut sprint = game(GameMode::Sprint);
+        sprint.lines = 39;
+        sprint.tick();
+        assert_eq!(sprint.state, GameState::Playing);
+
+        sprint.lines = 40;
+        sprint.tick();
+        assert_eq!(sprint.state, GameState::Complete);
+    }
+
+    #[test]
+    fn ultra_completes_when_time_runs_out() {
+        let mut ultra = game(GameMode::Ultra);
+        ultra.time = ULTRA_TIME;
+        ultra.tick();
+        assert_eq!(ultra.state, GameState::Complete);
+    }
+
+    #[test]
+    fn zen_never_tops_out() {
+        let mut zen = game(GameMode::Zen);
+        for _ in 0..200 {
+            zen.hard_drop();
+        }
+        assert_eq!(zen.state, GameState::Playing);
+    }
+
+    fn garbage_rows(game: &Game) -> usize {
+        game.table
+            .iter()
+            .filter(|row| row.contains(&Cell::Garbage))
+            .count()
+    }
+
+    #[test]
+    fn dig_keeps_garbage_stocked_until_the_goal() {
+        let mut dig = game(GameMode::Dig(18));
+        assert_eq!(garbage_rows(&dig), 10);
+
+        // Clear the bottom garbage row by filling its hole
+        let bottom = dig.table.len() - 1;
+        dig.table[bottom].fill(Cell::Garbage);
+        dig.hard_drop();
+        assert_eq!(dig.garbage_cleared, 1);
+        assert_eq!(garbage_rows(&dig), 10);
+
+        // Only as much garbage as is left to clear gets added
+        dig.garbage_cleared = 17;
+        dig.table.iter_mut().for_each(|row| row.fill(Cell::Empty));
+        dig.hard_drop();
+        assert_eq!(garbage_rows(&dig), 1);
+    }
+
+    #[test]
+    fn dig_completes_when_the_garbage_is_gone() {
+        let mut dig = game(GameMode::Dig(10));
+        dig.garbage_cleared = 9;
+        assert_eq!(dig.mode.garbage_left(&dig), Some(1));
+
+        dig.garbage_cleared = 10;
+        dig.tick();
+        assert_eq!(dig.state, GameState::Complete);
+    }
+}
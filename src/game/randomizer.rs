@@ -0,0 +1,204 @@
+use crate::game::shape::PieceKind;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// TGM starts its history as if these had just been dealt, so the first pieces
+/// lean away from S and Z
+const TGM_HISTORY: [PieceKind; 4] = [PieceKind::Z, PieceKind::S, PieceKind::Z, PieceKind::S];
+const TGM_REROLLS: usize = 6;
+
+/// Decides which piece comes next. The generator owns the random source and
+/// lends it out so every policy draws from the same stream
+pub trait Randomizer: Send + Sync {
+    fn next_kind(&mut self, rng: &mut dyn RngCore) -> PieceKind;
+}
+
+/// Which randomizer a game deals its pieces with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RandomizerKind {
+    /// One of each piece per bag of 7
+    #[default]
+    #[serde(rename = "7-bag")]
+    SevenBag,
+    /// Two of each piece per bag of 14
+    #[serde(rename = "14-bag")]
+    FourteenBag,
+    /// Every piece equally likely, no memory at all
+    Uniform,
+    /// Rerolls once when the roll repeats the previous piece
+    Nes,
+    /// Rerolls up to six times while the roll is among the last four pieces
+    Tgm,
+}
+
+impl RandomizerKind {
+    pub const ALL: [RandomizerKind; 5] = [
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::Uniform,
+        RandomizerKind::Nes,
+        RandomizerKind::Tgm,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RandomizerKind::SevenBag => "7-bag",
+            RandomizerKind::FourteenBag => "14-bag",
+            RandomizerKind::Uniform => "uniform",
+            RandomizerKind::Nes => "nes",
+            RandomizerKind::Tgm => "tgm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        RandomizerKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+
+    pub fn build(self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::SevenBag => Box::new(Bag::new(1)),
+            RandomizerKind::FourteenBag => Box::new(Bag::new(2)),
+            RandomizerKind::Uniform => Box::new(Uniform),
+            RandomizerKind::Nes => Box::new(Nes { previous: None }),
+            RandomizerKind::Tgm => Box::new(Tgm {
+                history: TGM_HISTORY,
+                first: true,
+            }),
+        }
+    }
+}
+
+/// Deals shuffled bags holding `copies` of every piece, a new bag only once the
+/// last one is empty
+pub struct Bag {
+    copies: usize,
+    bag: Vec<PieceKind>,
+}
+
+impl Bag {
+    pub fn new(copies: usize) -> Self {
+        Self {
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next_kind(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        if self.bag.is_empty() {
+            self.bag = PieceKind::ALL.repeat(self.copies);
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().expect("a fresh bag is never empty")
+    }
+}
+
+pub struct Uniform;
+
+impl Randomizer for Uniform {
+    fn next_kind(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        *PieceKind::ALL.choose(rng).expect("there are seven pieces")
+    }
+}
+
+/// The NES rolls an eighth "reroll" value as well as the seven pieces. Rolling it
+/// or the previous piece triggers one plain reroll among the seven
+pub struct Nes {
+    previous: Option<PieceKind>,
+}
+
+impl Randomizer for Nes {
+    fn next_kind(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        let roll = (rng.next_u32() % (PieceKind::ALL.len() as u32 + 1)) as usize;
+        let kind = match PieceKind::ALL.get(roll) {
+            Some(&kind) if Some(kind) != self.previous => kind,
+            _ => *PieceKind::ALL.choose(rng).expect("there are seven pieces"),
+        };
+        self.previous = Some(kind);
+        kind
+    }
+}
+
+/// TGM keeps the last four pieces and rerolls while the roll is one of them,
+/// giving up after six rerolls. The first piece is never S, Z or O
+pub struct Tgm {
+    history: [PieceKind; 4],
+    first: bool,
+}
+
+impl Randomizer for Tgm {
+    fn next_kind(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        let kind = if self.first {
+            self.first = false;
+            *[PieceKind::I, PieceKind::J, PieceKind::L, PieceKind::T]
+                .choose(rng)
+                .expect("there are four first pieces")
+        } else {
+            let mut kind = *PieceKind::ALL.choose(rng).expect("there are seven pieces");
+            for _ in 0..TGM_REROLLS {
+                if !self.history.contains(&kind) {
+                    break;
+                }
+                kind = *PieceKind::ALL.choose(rng).expect("there are seven pieces");
+            }
+            kind
+        };
+
+        self.history.rotate_left(1);
+        self.history[3] = kind;
+        kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn deal(kind: RandomizerKind, count: usize, seed: u64) -> Vec<PieceKind> {
+        let mut randomizer = kind.build();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        (0..count).map(|_| randomizer.next_kind(&mut rng)).collect()
+    }
+
+    fn count_of(kinds: &[PieceKind], kind: PieceKind) -> usize {
+        kinds.iter().filter(|&&other| other == kind).count()
+    }
+
+    #[test]
+    fn fourteen_bag_deals_two_of_each() {
+        for bag in deal(RandomizerKind::FourteenBag, 14 * 500, 0).chunks(14) {
+            for kind in PieceKind::ALL {
+                assert_eq!(count_of(bag, kind), 2, "uneven bag {bag:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_never_starts_with_s_z_or_o() {
+        for seed in 0..500 {
+            let first = deal(RandomizerKind::Tgm, 1, seed)[0];
+            assert!(![PieceKind::S, PieceKind::Z, PieceKind::O].contains(&first));
+        }
+    }
+
+    #[test]
+    fn every_randomizer_deals_every_piece() {
+        for (kind, seed) in RandomizerKind::ALL.into_iter().zip(0..) {
+            let dealt = deal(kind, 7 * 200, seed);
+            for piece in PieceKind::ALL {
+                assert!(
+                    dealt.contains(&piece),
+                    "{} never dealt {piece:?}",
+                    kind.name()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::game::control::{BoardSize, Ruleset};
+use crate::game::mode::GameMode;
+use crate::game::Game;
+use std::time::Duration;
+
+/// Playback speeds the viewer steps through, as multiples of real time
+pub const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+const NORMAL_SPEED: usize = 2;
+
+/// One frame at 60 fps, the step size of frame by frame playback
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+/// A player input that changes the game. Gravity and lock delay follow from
+/// the game clock, so these and their times are all a replay needs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    Hold,
+}
+
+impl Input {
+    pub const ALL: [Input; 8] = [
+        Input::MoveLeft,
+        Input::MoveRight,
+        Input::SoftDrop,
+        Input::HardDrop,
+        Input::RotateClockwise,
+        Input::RotateCounterClockwise,
+        Input::Rotate180,
+        Input::Hold,
+    ];
+
+    /// Single letter used for the input in replay files
+    pub fn code(self) -> char {
+        match self {
+            Input::MoveLeft => 'l',
+            Input::MoveRight => 'r',
+            Input::SoftDrop => 's',
+            Input::HardDrop => 'd',
+            Input::RotateClockwise => 'c',
+            Input::RotateCounterClockwise => 'a',
+            Input::Rotate180 => 'f',
+            Input::Hold => 'h',
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Self> {
+        Input::ALL.into_iter().find(|input| input.code() == code)
+    }
+}
+
+/// Write an input log as text: each input is the milliseconds since the one
+/// before it followed by its letter, e.g. `412l0l96c530d`
+pub fn encode_inputs(inputs: &[(Duration, Input)]) -> String {
+    let mut encoded = String::new();
+    let mut last = 0;
+    for &(time, input) in inputs {
+        let millis = time.as_millis() as u64;
+        encoded.push_str(&millis.saturating_sub(last).to_string());
+        encoded.push(input.code());
+        last = millis;
+    }
+    encoded
+}
+
+/// Read an input log written by `encode_inputs`, `None` if it is malformed
+pub fn decode_inputs(encoded: &str) -> Option<Vec<(Duration, Input)>> {
+    let mut inputs = Vec::new();
+    let mut millis: u64 = 0;
+    let mut delta = String::new();
+    for c in encoded.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            delta.push(c);
+            continue;
+        }
+        millis = millis.checked_add(delta.parse().ok()?)?;
+        delta.clear();
+        inputs.push((Duration::from_millis(millis), Input::from_code(c)?));
+    }
+    delta.is_empty().then_some(inputs)
+}
+
+/// Plays a recorded game back by feeding its inputs to a fresh game with the
+/// same seed at the same game times. Seeking backwards replays from the start
+pub struct ReplayPlayer {
+    board: BoardSize,
+    mode: GameMode,
+    ruleset: Ruleset,
+    seed: u64,
+    inputs: Vec<(Duration, Input)>,
+    next_input: usize,
+    pub game: Game,
+    pub position: Duration,
+    pub length: Duration,
+    pub paused: bool,
+    speed: usize, // Index into SPEEDS
+}
+
+impl ReplayPlayer {
+    pub fn new(
+        board: BoardSize,
+        mode: GameMode,
+        ruleset: Ruleset,
+        seed: u64,
+        inputs: Vec<(Duration, Input)>,
+        length: Duration,
+    ) -> Self {
+        let length = inputs.last().map_or(length, |&(time, _)| time.max(length));
+        Self {
+            board,
+            mode,
+            ruleset,
+            seed,
+            inputs,
+            next_input: 0,
+            game: Game::new(board, mode, ruleset, seed),
+            position: Duration::ZERO,
+            length,
+            paused: false,
+            speed: NORMAL_SPEED,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.length
+    }
+
+    /// Move playback by `elapsed` real time at the current speed
+    pub fn update(&mut self, elapsed: Duration) {
+        if !self.paused {
+            self.seek(self.position + elapsed.mul_f64(self.speed()));
+        }
+    }
+
+    /// Pause and move forward by one frame
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.seek(self.position + FRAME);
+    }
+
+    /// Jump to `position`, clamped to the length of the replay
+    pub fn seek(&mut self, position: Duration) {
+        let position = position.min(self.length);
+        if position < self.position {
+            self.game = Game::new(self.board, self.mode, self.ruleset, self.seed);
+            self.next_input = 0;
+        }
+
+        while let Some(&(time, input)) = self.inputs.get(self.next_input) {
+            if time > position {
+                break;
+            }
+            self.game.advance(time);
+            self.game.apply(input);
+            self.next_input += 1;
+        }
+        self.game.advance(position);
+        self.position = position;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::shape::Rotation;
+
+    #[test]
+    fn input_logs_round_trip() {
+        let inputs = vec![
+            (Duration::from_millis(412), Input::MoveLeft),
+            (Duration::from_millis(412), Input::MoveLeft),
+            (Duration::from_millis(508), Input::RotateClockwise),
+            (Duration::from_millis(1038), Input::HardDrop),
+        ];
+        let encoded = encode_inputs(&inputs);
+
+        assert_eq!(encoded, "412l0l96c530d");
+        assert_eq!(decode_inputs(&encoded), Some(inputs));
+        assert_eq!(decode_inputs("12x"), None);
+        assert_eq!(decode_inputs("12l5"), None);
+    }
+
+    /// Play a short game with inputs spread over time, the way a player would
+    fn play(game: &mut Game) {
+        for step in 0..400u64 {
+            game.advance(Duration::from_millis(step * 37));
+            match step % 7 {
+                0 => game.shift(-1),
+                1 => game.rotate_shape(Rotation::Clockwise),
+                2 => game.soft_drop(),
+                3 if step % 3 == 0 => game.hard_drop(),
+                4 => game.shift(1),
+                5 if step % 11 == 0 => game.hold_shape(),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn replays_reproduce_the_game() {
+        let ruleset = Ruleset::default();
+        let mut game = Game::new(BoardSize::default(), GameMode::Marathon, ruleset, 42);
+        play(&mut game);
+        assert!(!game.input_log.is_empty());
+
+        let inputs = decode_inputs(&encode_inputs(&game.input_log)).unwrap();
+        let mut player = ReplayPlayer::new(
+            BoardSize::default(),
+            GameMode::Marathon,
+            ruleset,
+            42,
+            inputs,
+            game.time,
+        );
+        player.seek(player.length);
+
+        assert_eq!(player.game.table, game.table);
+        assert_eq!(player.game.score, game.score);
+        assert_eq!(player.game.state, game.state);
+        assert_eq!(player.game.input_log, game.input_log);
+    }
+
+    #[test]
+    fn seeking_back_matches_playing_forward() {
+        let mut game = Game::new(
+            BoardSize::default(),
+            GameMode::Sprint,
+            Ruleset::default(),
+            7,
+        );
+        play(&mut game);
+
+        let mut player = ReplayPlayer::new(
+            BoardSize::default(),
+            GameMode::Sprint,
+            Ruleset::default(),
+            7,
+            game.input_log.clone(),
+            game.time,
+        );
+        let middle = player.length / 2;
+        player.seek(middle);
+        let table = player.game.table.clone();
+
+        player.seek(player.length);
+        player.seek(middle);
+        assert_eq!(player.game.table, table);
+    }
+}
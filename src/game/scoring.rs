@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+/// Guideline base points for clearing 1 to 4 lines at once, multiplied by the level
+const LINE_CLEAR_POINTS: [u32; 5] = [0, 100, 300, 500, 800];
+/// Guideline base points for a T-spin clearing 0 to 3 lines
+const TSPIN_POINTS: [u32; 4] = [400, 800, 1200, 1600];
+/// Guideline base points for a T-spin Mini clearing 0 to 2 lines
+const TSPIN_MINI_POINTS: [u32; 3] = [100, 200, 400];
+/// Points per step of an ongoing combo, multiplied by the level
+const COMBO_POINTS: u32 = 50;
+
+/// Garbage lines sent for clearing 1 to 4 lines at once
+const LINE_CLEAR_ATTACK: [u32; 5] = [0, 0, 1, 2, 4];
+/// Garbage lines sent for a T-spin clearing 0 to 3 lines
+const TSPIN_ATTACK: [u32; 4] = [0, 2, 4, 6];
+/// Garbage lines sent for a T-spin Mini clearing 0 to 2 lines
+const TSPIN_MINI_ATTACK: [u32; 3] = [0, 0, 1];
+/// Extra garbage lines per combo step, the last value repeats for longer combos
+const COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+
+/// How long a clear stays on screen after it happens
+const LABEL_DURATION: Duration = Duration::from_secs(2);
+
+/// Result of the 3-corner test when a T piece locks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
+/// Base points for a lock, before the level multiplier
+pub fn clear_points(lines: usize, tspin: TSpin) -> u32 {
+    match tspin {
+        TSpin::None => LINE_CLEAR_POINTS[lines.min(4)],
+        TSpin::Mini => TSPIN_MINI_POINTS[lines.min(2)],
+        TSpin::Full => TSPIN_POINTS[lines.min(3)],
+    }
+}
+
+/// Tetrises and T-spins that clear lines keep a back-to-back chain going
+pub fn is_difficult(lines: usize, tspin: TSpin) -> bool {
+    lines >= 4 || (lines > 0 && tspin != TSpin::None)
+}
+
+/// Back-to-back difficult clears are worth one and a half times their base points
+pub fn back_to_back_points(points: u32) -> u32 {
+    points * 3 / 2
+}
+
+/// Bonus for the given step of a combo; the first clear of a chain is step 0
+pub fn combo_points(combo: u32) -> u32 {
+    COMBO_POINTS * combo
+}
+
+/// Garbage lines a lock would send in a versus game, following the guideline
+/// attack table with one extra line for back-to-back
+pub fn attack(lines: usize, tspin: TSpin, back_to_back: bool, combo: u32) -> u32 {
+    if lines == 0 {
+        return 0;
+    }
+
+    let base = match tspin {
+        TSpin::None => LINE_CLEAR_ATTACK[lines.min(4)],
+        TSpin::Mini => TSPIN_MINI_ATTACK[lines.min(2)],
+        TSpin::Full => TSPIN_ATTACK[lines.min(3)],
+    };
+    let combo = COMBO_ATTACK[(combo as usize).min(COMBO_ATTACK.len() - 1)];
+    base + combo + u32::from(back_to_back)
+}
+
+/// The last scoring lock, kept around so the renderer can announce it
+#[derive(Clone, Debug)]
+pub struct ClearEvent {
+    pub lines: usize,
+    pub tspin: TSpin,
+    pub combo: u32,
+    pub back_to_back: bool,
+    pub at: Duration, // Game time of the lock
+}
+
+impl ClearEvent {
+    pub fn is_visible(&self, now: Duration) -> bool {
+        now.saturating_sub(self.at) < LABEL_DURATION
+    }
+
+    pub fn label(&self) -> String {
+        let lines = match self.lines {
+            0 => "",
+            1 => "Single",
+            2 => "Double",
+            3 => "Triple",
+            _ => "Tetris",
+        };
+
+        match self.tspin {
+            TSpin::None => lines.to_string(),
+            TSpin::Mini => format!("T-Spin Mini {}", lines).trim_end().to_string(),
+            TSpin::Full => format!("T-Spin {}", lines).trim_end().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_clears_with_the_guideline_table() {
+        assert_eq!(clear_points(0, TSpin::None), 0);
+        assert_eq!(clear_points(1, TSpin::None), 100);
+        assert_eq!(clear_points(4, TSpin::None), 800);
+        assert_eq!(clear_points(0, TSpin::Full), 400);
+        assert_eq!(clear_points(2, TSpin::Full), 1200);
+        assert_eq!(clear_points(1, TSpin::Mini), 200);
+    }
+
+    #[test]
+    fn only_tetrises_and_tspin_clears_are_difficult() {
+        assert!(is_difficult(4, TSpin::None));
+        assert!(is_difficult(1, TSpin::Mini));
+        assert!(!is_difficult(3, TSpin::None));
+        assert!(!is_difficult(0, TSpin::Full));
+        assert_eq!(back_to_back_points(800), 1200);
+        assert_eq!(combo_points(0), 0);
+        assert_eq!(combo_points(3), 150);
+    }
+
+    #[test]
+    fn labels_name_the_clear() {
+        let clear = |lines, tspin| ClearEvent {
+            lines,
+            tspin,
+            combo: 0,
+            back_to_back: false,
+            at: Duration::ZERO,
+        };
+        assert_eq!(clear(4, TSpin::None).label(), "Tetris");
+        assert_eq!(clear(2, TSpin::Full).label(), "T-Spin Double");
+        assert_eq!(clear(0, TSpin::Mini).label(), "T-Spin Mini");
+        assert!(clear(1, TSpin::None).is_visible(Duration::from_secs(1)));
+        assert!(!clear(1, TSpin::None).is_visible(LABEL_DURATION));
+    }
+}
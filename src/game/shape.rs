@@ -1,18 +1,96 @@
+use crate::game::randomizer::{Randomizer, RandomizerKind};
 use crossterm::style::Color;
-use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PieceKind {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 7] = [
+        PieceKind::I,
+        PieceKind::O,
+        PieceKind::T,
+        PieceKind::S,
+        PieceKind::Z,
+        PieceKind::J,
+        PieceKind::L,
+    ];
+
+    /// Default color of the piece, used by the classic theme
+    pub fn color(self) -> Color {
+        match self {
+            PieceKind::I => Color::White,
+            PieceKind::O => Color::Cyan,
+            PieceKind::T => Color::Yellow,
+            PieceKind::S => Color::Green,
+            PieceKind::Z => Color::Red,
+            PieceKind::J => Color::Magenta,
+            PieceKind::L => Color::Blue,
+        }
+    }
+}
+
+/// SRS orientation states: spawn (0), clockwise (R), 180 (2) and counter-clockwise (L)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Spawn,
+    Right,
+    Two,
+    Left,
+}
+
+impl Orientation {
+    pub fn clockwise(self) -> Self {
+        match self {
+            Orientation::Spawn => Orientation::Right,
+            Orientation::Right => Orientation::Two,
+            Orientation::Two => Orientation::Left,
+            Orientation::Left => Orientation::Spawn,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
+impl Rotation {
+    /// Number of clockwise quarter turns equivalent to this rotation
+    pub fn quarter_turns(self) -> usize {
+        match self {
+            Rotation::Clockwise => 1,
+            Rotation::Half => 2,
+            Rotation::CounterClockwise => 3,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Shape {
+    pub kind: PieceKind,
     pub array: Vec<Vec<u8>>,
     pub width: usize,
     pub height: usize,
     pub row: isize,
     pub col: isize,
-    pub color: Color,
+    pub orientation: Orientation,
 }
 
 impl Shape {
-    pub fn new(shape_data: &[&[u8]], color: Color) -> Self {
+    pub fn new(kind: PieceKind, shape_data: &[&[u8]]) -> Self {
         let array = shape_data
             .iter()
             .map(|row| row.to_vec())
@@ -20,50 +98,179 @@ impl Shape {
         let height = shape_data.len();
         let width = shape_data[0].len();
         Self {
+            kind,
             array,
             width,
             height,
             row: 0,
-            col: 5 - width as isize / 2,
-            color,
+            col: 0,
+            orientation: Orientation::Spawn,
+        }
+    }
+
+    /// Rotate the matrix and update the orientation state
+    pub fn rotate(&mut self, rotation: Rotation) {
+        for _ in 0..rotation.quarter_turns() {
+            self.rotate_clockwise();
         }
     }
 
-    pub fn rotate(&mut self) {
+    /// Return the shape to its spawn orientation
+    pub fn reset(&mut self) {
+        while self.orientation != Orientation::Spawn {
+            self.rotate_clockwise();
+        }
+    }
+
+    fn rotate_clockwise(&mut self) {
         let mut new_array = vec![vec![0; self.height]; self.width];
-        for i in 0..self.height {
-            for j in 0..self.width {
-                new_array[j][self.height - 1 - i] = self.array[i][j];
+        for (i, row) in self.array.iter().enumerate() {
+            for (j, &cell) in row.iter().enumerate() {
+                new_array[j][self.height - 1 - i] = cell;
             }
         }
 
         self.array = new_array;
         std::mem::swap(&mut self.width, &mut self.height);
+        self.orientation = self.orientation.clockwise();
     }
 }
 
+/// The seven tetrominoes in their spawn orientation
+pub fn tetrominoes() -> Vec<Shape> {
+    vec![
+        Shape::new(PieceKind::S, &[&[0, 1, 1], &[1, 1, 0], &[0, 0, 0]]),
+        Shape::new(PieceKind::Z, &[&[1, 1, 0], &[0, 1, 1], &[0, 0, 0]]),
+        Shape::new(PieceKind::T, &[&[0, 1, 0], &[1, 1, 1], &[0, 0, 0]]),
+        Shape::new(PieceKind::L, &[&[0, 0, 1], &[1, 1, 1], &[0, 0, 0]]),
+        Shape::new(PieceKind::J, &[&[1, 0, 0], &[1, 1, 1], &[0, 0, 0]]),
+        Shape::new(PieceKind::O, &[&[1, 1], &[1, 1]]),
+        Shape::new(
+            PieceKind::I,
+            &[&[0, 0, 0, 0], &[1, 1, 1, 1], &[0, 0, 0, 0], &[0, 0, 0, 0]],
+        ),
+    ]
+}
+
+/// Deals shapes in the order the randomizer picks them. Peeked shapes are drawn
+/// from the randomizer ahead of time and queued, so peeking never changes what
+/// gets dealt
 pub struct ShapeGenerator {
     shapes: Vec<Shape>,
-    permutation: Vec<Shape>,
+    randomizer: Box<dyn Randomizer>,
+    rng: ChaCha8Rng,
+    queue: VecDeque<Shape>, // Front is dealt next
 }
 
 impl ShapeGenerator {
-    pub fn new(shapes: Vec<Shape>) -> Self {
+    /// The same shapes, randomizer and seed always deal the same sequence. The
+    /// generator is ChaCha8 rather than `StdRng`, whose algorithm may change
+    /// between rand versions and would quietly deal shared seeds differently
+    pub fn new(shapes: Vec<Shape>, randomizer: RandomizerKind, seed: u64) -> Self {
         ShapeGenerator {
             shapes,
-            permutation: Vec::new(),
+            randomizer: randomizer.build(),
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            queue: VecDeque::new(),
         }
     }
 
-    pub fn generate_permutation(&mut self) -> Vec<Shape> {
-        let mut rng = rand::rng();
-        let mut shapes = self.shapes.clone();
-        shapes.shuffle(&mut rng);
-        self.permutation = shapes;
-        self.permutation.clone()
+    /// Ask the randomizer for one more shape at the back of the queue
+    fn draw(&mut self) {
+        let kind = self.randomizer.next_kind(&mut self.rng);
+        let shape = self
+            .shapes
+            .iter()
+            .find(|shape| shape.kind == kind)
+            .expect("every piece kind has a shape");
+        self.queue.push_back(shape.clone());
     }
 
-    pub fn next_shape(&mut self) -> Option<Shape> {
-        self.permutation.pop()
+    pub fn next_shape(&mut self) -> Shape {
+        if self.queue.is_empty() {
+            self.draw();
+        }
+        self.queue.pop_front().expect("a shape was just drawn")
+    }
+
+    /// Look at the next `count` shapes without taking them
+    pub fn peek(&mut self, count: usize) -> Vec<Shape> {
+        while self.queue.len() < count {
+            self.draw();
+        }
+        self.queue.iter().take(count).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn kinds(shapes: &[Shape]) -> Vec<PieceKind> {
+        shapes.iter().map(|shape| shape.kind).collect()
+    }
+
+    /// Check that every run of seven dealt pieces holds each piece exactly once
+    pub(crate) fn assert_complete_bags(kinds: &[PieceKind]) {
+        for bag in kinds.chunks(7) {
+            let complete =
+                bag.len() == 7 && tetrominoes().iter().all(|shape| bag.contains(&shape.kind));
+            assert!(complete, "incomplete bag {bag:?}");
+        }
+    }
+
+    #[test]
+    fn every_bag_is_a_complete_set() {
+        for seed in 0..10 {
+            let mut generator = ShapeGenerator::new(tetrominoes(), RandomizerKind::SevenBag, seed);
+            let dealt: Vec<PieceKind> =
+                (0..7 * 1000).map(|_| generator.next_shape().kind).collect();
+
+            assert_complete_bags(&dealt);
+        }
+    }
+
+    #[test]
+    fn peeking_keeps_bag_boundaries() {
+        for seed in 0..10 {
+            let mut generator = ShapeGenerator::new(tetrominoes(), RandomizerKind::SevenBag, seed);
+            let mut dealt = Vec::new();
+
+            // Peek at varying depths between draws, the way the next queue does
+            for i in 0..7 * 1000 {
+                generator.peek(i % 13 + 1);
+                dealt.push(generator.next_shape().kind);
+            }
+
+            assert_complete_bags(&dealt);
+        }
+    }
+
+    /// Shared seeds and saved replays rely on this exact sequence. If it changes,
+    /// so must the replay format version
+    #[test]
+    fn seeds_deal_a_fixed_sequence() {
+        let mut generator = ShapeGenerator::new(tetrominoes(), RandomizerKind::SevenBag, 42);
+        let dealt: Vec<PieceKind> = (0..14).map(|_| generator.next_shape().kind).collect();
+
+        use PieceKind::*;
+        assert_eq!(dealt, [S, I, L, Z, J, O, T, T, J, O, S, L, Z, I]);
+    }
+
+    #[test]
+    fn peek_matches_what_is_dealt() {
+        let mut generator = ShapeGenerator::new(tetrominoes(), RandomizerKind::SevenBag, 3);
+
+        for _ in 0..500 {
+            let peeked = kinds(&generator.peek(20));
+            assert_eq!(
+                kinds(&generator.peek(20)),
+                peeked,
+                "peeking must not consume"
+            );
+
+            let dealt: Vec<PieceKind> = (0..3).map(|_| generator.next_shape().kind).collect();
+            assert_eq!(dealt, peeked[..3]);
+        }
     }
 }
@@ -0,0 +1,124 @@
+use crate::game::shape::{Orientation, PieceKind};
+
+/// Kick offsets are `(x, y)` pairs as written in the SRS tables: `x` to the right
+/// and `y` upwards. Callers must flip `y` to move in board rows.
+pub type Kick = (isize, isize);
+
+const NO_KICKS: [Kick; 1] = [(0, 0)];
+
+const JLSTZ_0_R: [Kick; 5] = [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+const JLSTZ_R_0: [Kick; 5] = [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+const JLSTZ_R_2: [Kick; 5] = [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+const JLSTZ_2_R: [Kick; 5] = [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+const JLSTZ_2_L: [Kick; 5] = [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+const JLSTZ_L_2: [Kick; 5] = [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+const JLSTZ_L_0: [Kick; 5] = [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+const JLSTZ_0_L: [Kick; 5] = [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+
+const I_0_R: [Kick; 5] = [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)];
+const I_R_0: [Kick; 5] = [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)];
+const I_R_2: [Kick; 5] = [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)];
+const I_2_R: [Kick; 5] = [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)];
+const I_2_L: [Kick; 5] = [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)];
+const I_L_2: [Kick; 5] = [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)];
+const I_L_0: [Kick; 5] = [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)];
+const I_0_L: [Kick; 5] = [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)];
+
+// 180 degree kicks are not part of guideline SRS; these follow the widely used SRS+ tables
+const JLSTZ_0_2: [Kick; 6] = [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)];
+const JLSTZ_2_0: [Kick; 6] = [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)];
+const JLSTZ_R_L: [Kick; 6] = [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)];
+const JLSTZ_L_R: [Kick; 6] = [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)];
+
+const I_0_2: [Kick; 2] = [(0, 0), (0, 1)];
+const I_2_0: [Kick; 2] = [(0, 0), (0, -1)];
+const I_R_L: [Kick; 2] = [(0, 0), (1, 0)];
+const I_L_R: [Kick; 2] = [(0, 0), (-1, 0)];
+
+/// Return the wall kick offsets to try, in order, when `kind` rotates from `from` to `to`
+pub fn kicks(kind: PieceKind, from: Orientation, to: Orientation) -> &'static [Kick] {
+    use Orientation::*;
+
+    match kind {
+        PieceKind::O => &NO_KICKS,
+        PieceKind::I => match (from, to) {
+            (Spawn, Right) => &I_0_R,
+            (Right, Spawn) => &I_R_0,
+            (Right, Two) => &I_R_2,
+            (Two, Right) => &I_2_R,
+            (Two, Left) => &I_2_L,
+            (Left, Two) => &I_L_2,
+            (Left, Spawn) => &I_L_0,
+            (Spawn, Left) => &I_0_L,
+            (Spawn, Two) => &I_0_2,
+            (Two, Spawn) => &I_2_0,
+            (Right, Left) => &I_R_L,
+            (Left, Right) => &I_L_R,
+            _ => &NO_KICKS,
+        },
+        _ => match (from, to) {
+            (Spawn, Right) => &JLSTZ_0_R,
+            (Right, Spawn) => &JLSTZ_R_0,
+            (Right, Two) => &JLSTZ_R_2,
+            (Two, Right) => &JLSTZ_2_R,
+            (Two, Left) => &JLSTZ_2_L,
+            (Left, Two) => &JLSTZ_L_2,
+            (Left, Spawn) => &JLSTZ_L_0,
+            (Spawn, Left) => &JLSTZ_0_L,
+            (Spawn, Two) => &JLSTZ_0_2,
+            (Two, Spawn) => &JLSTZ_2_0,
+            (Right, Left) => &JLSTZ_R_L,
+            (Left, Right) => &JLSTZ_L_R,
+            _ => &NO_KICKS,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Orientation::*;
+
+    const QUARTER_TURNS: [(Orientation, Orientation); 4] =
+        [(Spawn, Right), (Right, Two), (Two, Left), (Left, Spawn)];
+
+    #[test]
+    fn every_rotation_tries_no_kick_first() {
+        let orientations = [Spawn, Right, Two, Left];
+        for kind in [PieceKind::T, PieceKind::I, PieceKind::O] {
+            for from in orientations {
+                for to in orientations {
+                    assert_eq!(kicks(kind, from, to)[0], (0, 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn turning_back_mirrors_the_kicks() {
+        for kind in [
+            PieceKind::J,
+            PieceKind::L,
+            PieceKind::S,
+            PieceKind::T,
+            PieceKind::Z,
+            PieceKind::I,
+        ] {
+            for (from, to) in QUARTER_TURNS {
+                let forward = kicks(kind, from, to);
+                let back = kicks(kind, to, from);
+                assert_eq!(forward.len(), 5);
+                for (&(x, y), &(back_x, back_y)) in forward.iter().zip(back) {
+                    assert_eq!((x, y), (-back_x, -back_y), "{kind:?} {from:?} -> {to:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn uses_the_guideline_tables() {
+        assert_eq!(kicks(PieceKind::T, Spawn, Right)[3], (0, -2));
+        assert_eq!(kicks(PieceKind::I, Spawn, Right)[4], (1, 2));
+        assert_eq!(kicks(PieceKind::O, Spawn, Right), &[(0, 0)]);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::game::scoring::{self, TSpin};
+use std::time::Duration;
+
+/// Running totals for one game, shown on the results screen
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub pieces: u32,
+    pub inputs: u32,      // Gameplay key presses, auto repeat excluded
+    pub clears: [u32; 4], // Singles, doubles, triples and tetrises
+    pub tspins: u32,      // T-spins of any size, minis included
+    pub max_combo: u32,
+    pub back_to_back: u32, // Back-to-back bonuses in the current chain
+    pub max_back_to_back: u32,
+    pub attack: u32, // Garbage lines the clears would send
+}
+
+impl Stats {
+    /// Count a locked piece and whatever it cleared
+    pub fn record_lock(
+        &mut self,
+        lines: usize,
+        tspin: TSpin,
+        combo: Option<u32>,
+        back_to_back: bool,
+    ) {
+        self.pieces += 1;
+        if tspin != TSpin::None {
+            self.tspins += 1;
+        }
+        if lines == 0 {
+            return;
+        }
+
+        self.clears[lines.min(4) - 1] += 1;
+
+        let combo = combo.unwrap_or(0);
+        self.max_combo = self.max_combo.max(combo);
+
+        if back_to_back {
+            self.back_to_back += 1;
+            self.max_back_to_back = self.max_back_to_back.max(self.back_to_back);
+        } else if !scoring::is_difficult(lines, tspin) {
+            self.back_to_back = 0;
+        }
+
+        self.attack += scoring::attack(lines, tspin, back_to_back, combo);
+    }
+
+    pub fn pieces_per_second(&self, time: Duration) -> f64 {
+        per(self.pieces as f64, time.as_secs_f64())
+    }
+
+    pub fn inputs_per_piece(&self) -> f64 {
+        per(self.inputs as f64, self.pieces as f64)
+    }
+
+    /// Attack per minute
+    pub fn attack_per_minute(&self, time: Duration) -> f64 {
+        per(self.attack as f64, time.as_secs_f64() / 60.0)
+    }
+}
+
+fn per(amount: f64, over: f64) -> f64 {
+    if over > 0.0 {
+        amount / over
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_back_to_back_chains() {
+        let mut stats = Stats::default();
+        stats.record_lock(4, TSpin::None, Some(0), false);
+        stats.record_lock(2, TSpin::Full, Some(0), true);
+        stats.record_lock(4, TSpin::None, Some(0), true);
+        assert_eq!(stats.max_back_to_back, 2);
+
+        // A plain clear breaks the chain, a lock without lines doesn't
+        stats.record_lock(0, TSpin::None, None, false);
+        assert_eq!(stats.back_to_back, 2);
+        stats.record_lock(1, TSpin::None, Some(0), false);
+        assert_eq!(stats.back_to_back, 0);
+        assert_eq!(stats.max_back_to_back, 2);
+    }
+
+    #[test]
+    fn counts_clears_and_attack() {
+        let mut stats = Stats::default();
+        stats.record_lock(1, TSpin::None, Some(0), false);
+        stats.record_lock(2, TSpin::None, Some(1), false);
+        stats.record_lock(2, TSpin::Full, Some(2), false);
+        stats.record_lock(4, TSpin::None, Some(3), true);
+
+        assert_eq!(stats.clears, [1, 2, 0, 1]);
+        assert_eq!(stats.tspins, 1);
+        assert_eq!(stats.max_combo, 3);
+        // 0 + 1 + (4 + 1 combo) + (4 + 1 combo + 1 back-to-back)
+        assert_eq!(stats.attack, 12);
+        assert_eq!(stats.pieces, 4);
+    }
+}
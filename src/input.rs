@@ -0,0 +1,224 @@
+use crate::game::Game;
+use std::time::{Duration, Instant};
+
+/// Timing for held movement keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputConfig {
+    /// Delayed Auto Shift: how long a direction must be held before it repeats
+    pub das: Duration,
+    /// Auto Repeat Rate: time between repeated moves once DAS is charged,
+    /// zero moves the shape straight to the wall
+    pub arr: Duration,
+    /// How many times faster than gravity a held soft drop falls
+    pub soft_drop_factor: u32,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            das: Duration::from_millis(167),
+            arr: Duration::from_millis(33),
+            soft_drop_factor: 20,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shift {
+    Left,
+    Right,
+}
+
+impl Shift {
+    fn dx(self) -> isize {
+        match self {
+            Shift::Left => -1,
+            Shift::Right => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HeldShift {
+    shift: Shift,
+    pressed_at: Instant,
+    last_repeat: Option<Instant>,
+}
+
+/// Turns key presses and releases into DAS/ARR movement and repeated soft drops.
+///
+/// Held keys can only be tracked when the terminal reports key releases. Without
+/// them every press moves once and repeats come from the terminal's own key repeat.
+/// Every call takes the time it happens at, so the timing doesn't depend on
+/// when the caller gets around to it
+pub struct InputHandler {
+    pub config: InputConfig,
+    pub tracks_releases: bool,
+    held: Vec<HeldShift>, // Most recently pressed last, it wins over the others
+    soft_drop_since: Option<Instant>,
+}
+
+impl InputHandler {
+    pub fn new(config: InputConfig, tracks_releases: bool) -> Self {
+        Self {
+            config,
+            tracks_releases,
+            held: Vec::new(),
+            soft_drop_since: None,
+        }
+    }
+
+    pub fn press_shift(&mut self, shift: Shift, game: &mut Game, now: Instant) {
+        game.shift(shift.dx());
+
+        if self.tracks_releases {
+            self.held.retain(|held| held.shift != shift);
+            self.held.push(HeldShift {
+                shift,
+                pressed_at: now,
+                last_repeat: None,
+            });
+        }
+    }
+
+    pub fn release_shift(&mut self, shift: Shift) {
+        self.held.retain(|held| held.shift != shift);
+    }
+
+    pub fn press_soft_drop(&mut self, game: &mut Game, now: Instant) {
+        game.soft_drop();
+
+        if self.tracks_releases {
+            self.soft_drop_since = Some(now);
+        }
+    }
+
+    pub fn release_soft_drop(&mut self) {
+        self.soft_drop_since = None;
+    }
+
+    /// Forget every held key, e.g. when the game is paused or restarted
+    pub fn clear(&mut self) {
+        self.held.clear();
+        self.soft_drop_since = None;
+    }
+
+    /// Apply auto repeat for whatever is still held at `now`
+    pub fn update(&mut self, game: &mut Game, now: Instant) {
+        if let Some(held) = self.held.last_mut() {
+            let charged_at = held.pressed_at + self.config.das;
+            if now >= charged_at {
+                if self.config.arr.is_zero() {
+                    Self::shift_to_wall(game, held.shift);
+                } else {
+                    // The first repeat fires as soon as DAS is charged
+                    let last = *held.last_repeat.get_or_insert_with(|| {
+                        game.shift(held.shift.dx());
+                        charged_at
+                    });
+
+                    let repeats = now.duration_since(last).as_nanos() / self.config.arr.as_nanos();
+                    for _ in 0..repeats {
+                        game.shift(held.shift.dx());
+                    }
+                    held.last_repeat = Some(last + self.config.arr * repeats as u32);
+                }
+            }
+        }
+
+        if let Some(since) = self.soft_drop_since {
+            let interval = game.tick_rate / self.config.soft_drop_factor.max(1);
+            if now.duration_since(since) >= interval {
+                self.soft_drop_since = Some(now);
+                game.soft_drop();
+            }
+        }
+    }
+
+    fn shift_to_wall(game: &mut Game, shift: Shift) {
+        // A change of row means the shape locked and a new one spawned
+        loop {
+            let (row, col) = (game.current.row, game.current.col);
+            game.shift(shift.dx());
+            if game.current.col == col || game.current.row != row {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::control::{BoardSize, Ruleset};
+    use crate::game::mode::GameMode;
+
+    const DAS: Duration = Duration::from_millis(100);
+    const ARR: Duration = Duration::from_millis(20);
+
+    /// A wide Zen board, so nothing falls and the walls are far away
+    fn setup(arr: Duration) -> (InputHandler, Game) {
+        let config = InputConfig {
+            das: DAS,
+            arr,
+            soft_drop_factor: 20,
+        };
+        let board = BoardSize {
+            width: 40,
+            ..BoardSize::default()
+        };
+        let game = Game::new(board, GameMode::Zen, Ruleset::default(), 0);
+        (InputHandler::new(config, true), game)
+    }
+
+    #[test]
+    fn das_charges_before_anything_repeats() {
+        let (mut input, mut game) = setup(ARR);
+        let start = game.current.col;
+        let pressed = Instant::now();
+
+        input.press_shift(Shift::Right, &mut game, pressed);
+        assert_eq!(game.current.col, start + 1);
+
+        input.update(&mut game, pressed + DAS - Duration::from_millis(1));
+        assert_eq!(game.current.col, start + 1);
+
+        input.update(&mut game, pressed + DAS);
+        assert_eq!(game.current.col, start + 2);
+    }
+
+    #[test]
+    fn arr_repeats_once_per_interval() {
+        let (mut input, mut game) = setup(ARR);
+        let start = game.current.col;
+        let pressed = Instant::now();
+
+        input.press_shift(Shift::Right, &mut game, pressed);
+        input.update(&mut game, pressed + DAS + ARR * 3 + ARR / 2);
+        assert_eq!(game.current.col, start + 5);
+
+        // Repeats already made aren't made again
+        input.update(&mut game, pressed + DAS + ARR * 3 + ARR / 2);
+        assert_eq!(game.current.col, start + 5);
+        input.update(&mut game, pressed + DAS + ARR * 4);
+        assert_eq!(game.current.col, start + 6);
+
+        input.release_shift(Shift::Right);
+        input.update(&mut game, pressed + DAS + ARR * 10);
+        assert_eq!(game.current.col, start + 6);
+    }
+
+    #[test]
+    fn zero_arr_moves_straight_to_the_wall() {
+        let (mut input, mut game) = setup(Duration::ZERO);
+        let pressed = Instant::now();
+
+        input.press_shift(Shift::Left, &mut game, pressed);
+        input.update(&mut game, pressed + DAS);
+
+        let mut further = game.current.clone();
+        further.col -= 1;
+        assert!(!game.is_valid_position(&further));
+        assert_eq!(game.stats.pieces, 0);
+    }
+}
@@ -0,0 +1,280 @@
+use crossterm::event::KeyCode;
+use std::collections::BTreeMap;
+
+/// Everything a key can be bound to during a game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    Hold,
+    Pause,
+    Restart,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+        Action::Restart,
+        Action::Quit,
+    ];
+
+    /// Name used for the action in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::RotateClockwise => "rotate_cw",
+            Action::RotateCounterClockwise => "rotate_ccw",
+            Action::Rotate180 => "rotate_180",
+            Action::Hold => "hold",
+            Action::Pause => "pause",
+            Action::Restart => "restart",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Whether the action controls the falling piece, as counted in input
+    /// statistics. Only the other actions work while the game is paused
+    pub fn moves_piece(self) -> bool {
+        !matches!(self, Action::Pause | Action::Restart | Action::Quit)
+    }
+}
+
+/// Map from actions to the keys that trigger them; an action may have several keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use KeyCode::*;
+
+        Self {
+            bindings: vec![
+                (Action::MoveLeft, vec![Left]),
+                (Action::MoveRight, vec![Right]),
+                (Action::SoftDrop, vec![Down]),
+                (Action::HardDrop, vec![Char(' ')]),
+                (Action::RotateClockwise, vec![Up, Char('x')]),
+                (Action::RotateCounterClockwise, vec![Char('z')]),
+                (Action::Rotate180, vec![Char('a')]),
+                (Action::Hold, vec![Char('c')]),
+                (Action::Pause, vec![Char('p')]),
+                (Action::Restart, vec![Char('r')]),
+                (Action::Quit, vec![Esc, Char('q')]),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Build bindings from the `[keys]` table of the config file on top of the
+    /// defaults, e.g. `move_left = ["Left", "a"]`. Actions missing from the table
+    /// keep their defaults and unknown names are skipped. A key listed for two
+    /// actions is ambiguous, so neither of them gets it
+    pub fn from_names(names: &BTreeMap<String, Vec<String>>) -> Self {
+        let listed: Vec<(Action, Vec<KeyCode>)> = names
+            .iter()
+            .filter_map(|(name, keys)| {
+                let codes = keys.iter().filter_map(|key| key_from_name(key)).collect();
+                Some((Action::from_name(name)?, codes))
+            })
+            .collect();
+        let ambiguous = |action: Action, code: &KeyCode| {
+            listed
+                .iter()
+                .any(|(other, codes)| *other != action && codes.contains(code))
+        };
+
+        let mut bindings = Self::default();
+        for (action, codes) in &listed {
+            let codes: Vec<KeyCode> = codes
+                .iter()
+                .filter(|code| !ambiguous(*action, code))
+                .copied()
+                .collect();
+            if !codes.is_empty() {
+                bindings.set(*action, codes);
+            }
+        }
+        bindings
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, keys)| keys.as_slice())
+    }
+
+    /// Bind `keys` to `action`, taking them away from any other action so a key
+    /// never does two things
+    pub fn set(&mut self, action: Action, keys: Vec<KeyCode>) {
+        for (bound, bound_keys) in self.bindings.iter_mut() {
+            if *bound != action {
+                bound_keys.retain(|code| !keys.contains(code));
+            }
+        }
+        match self.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+            Some((_, bound_keys)) => *bound_keys = keys,
+            None => self.bindings.push((action, keys)),
+        }
+    }
+
+    /// Human readable list of the keys bound to an action, e.g. `Esc/q`
+    pub fn describe(&self, action: Action) -> String {
+        self.keys(action)
+            .iter()
+            .map(|&code| key_name(code))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Return the action bound to a key, ignoring letter case
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        let code = normalize(code);
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&code))
+            .map(|(action, _)| *action)
+    }
+}
+
+fn normalize(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        code => code,
+    }
+}
+
+/// Parse a key name from the config file: a single character or one of the
+/// named keys such as `Left`, `Space` or `F1`
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    let code = match name.to_ascii_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "shift+tab" | "backtab" => KeyCode::BackTab,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        "delete" => KeyCode::Delete,
+        lower => {
+            let mut chars = lower.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('f'), Some(_)) => KeyCode::F(lower[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+    Some(code)
+}
+
+/// Inverse of `key_from_name`, used to show and save bindings
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::BackTab => "BackTab".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        code => format!("{:?}", code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().map(|key| key.to_string()).collect();
+                (action.to_string(), keys)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_key_names() {
+        assert_eq!(key_from_name("Space"), Some(KeyCode::Char(' ')));
+        assert_eq!(key_from_name("LEFT"), Some(KeyCode::Left));
+        assert_eq!(key_from_name("A"), Some(KeyCode::Char('a')));
+        assert_eq!(key_from_name("F5"), Some(KeyCode::F(5)));
+        assert_eq!(key_from_name("Fx"), None);
+        assert_eq!(key_from_name("nonsense"), None);
+
+        for code in [
+            KeyCode::Char(' '),
+            KeyCode::F(12),
+            KeyCode::PageUp,
+            KeyCode::Esc,
+        ] {
+            assert_eq!(key_from_name(&key_name(code)), Some(code));
+        }
+    }
+
+    #[test]
+    fn config_entries_replace_defaults() {
+        let bindings = KeyBindings::from_names(&names(&[
+            ("hold", &["Tab", "bogus"]),
+            ("not_an_action", &["h"]),
+            ("pause", &["bogus"]),
+        ]));
+
+        assert_eq!(bindings.action(KeyCode::Tab), Some(Action::Hold));
+        assert_eq!(bindings.action(KeyCode::Char('c')), None);
+        assert_eq!(bindings.action(KeyCode::Char('h')), None);
+        assert_eq!(bindings.action(KeyCode::Char('P')), Some(Action::Pause));
+    }
+
+    #[test]
+    fn a_rebound_key_leaves_its_default_action() {
+        let bindings = KeyBindings::from_names(&names(&[("move_left", &["a"])]));
+
+        assert_eq!(bindings.action(KeyCode::Char('a')), Some(Action::MoveLeft));
+        assert!(bindings.keys(Action::Rotate180).is_empty());
+    }
+
+    #[test]
+    fn keys_listed_twice_are_ignored() {
+        let bindings =
+            KeyBindings::from_names(&names(&[("hold", &["Tab", "v"]), ("rotate_180", &["v"])]));
+
+        assert_eq!(bindings.action(KeyCode::Char('v')), None);
+        assert_eq!(bindings.keys(Action::Hold), &[KeyCode::Tab]);
+        assert_eq!(bindings.keys(Action::Rotate180), &[KeyCode::Char('a')]);
+    }
+}
@@ -1,49 +1,209 @@
+mod config;
 mod game;
+mod input;
+mod keys;
 mod renderer;
+mod replays;
+mod scores;
+mod settings;
 use crate::renderer::render_gameover_menu;
-use crate::renderer::render_start_menu;
+use crate::renderer::{
+    render_leaderboard, render_message, render_replay, render_replay_list, render_settings_menu,
+    render_start_menu, START_MENU_ITEMS,
+};
 use crossterm::{
-    event::{self, KeyCode, KeyEvent},
+    event::{
+        self, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
+use game::control::BoardSize;
+use game::gravity::Gravity;
+use game::mode::GameMode;
+use game::randomizer::RandomizerKind;
+use game::shape::Rotation;
 use game::Game;
+use input::{InputHandler, Shift};
+use keys::Action;
 use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
 use renderer::render;
+use replays::Replay;
+use scores::HighScores;
+use settings::{cycle, Overrides, Settings, SettingsMenu};
 use std::io::{self, stdout};
+use std::path::Path;
 use std::time::{Duration, Instant};
 
+/// How far Left/Right jump in the replay viewer
+const REPLAY_SEEK_STEP: Duration = Duration::from_secs(5);
+
 fn main() -> io::Result<()> {
     enable_raw_mode()?;
     execute!(stdout(), EnterAlternateScreen)?;
 
+    // Key release events let us run DAS/ARR ourselves instead of relying on the
+    // terminal's key repeat
+    let tracks_releases = supports_keyboard_enhancement().unwrap_or(false);
+    if tracks_releases {
+        execute!(
+            stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )?;
+    }
+
+    // Settings come from the config file, command line flags override them for
+    // this run only. A config file that can't be read is never saved over
+    let (mut settings, settings_error) = match Settings::load() {
+        Ok(settings) => (settings, None),
+        Err(err) => (Settings::default(), Some(err)),
+    };
+    let mut overrides = Overrides::default();
+    let args: Vec<String> = std::env::args().collect();
+
+    // `--gravity guideline|classic|20g` picks the falling speed curve
+    overrides.gravity = arg_value(&args, "--gravity").and_then(Gravity::from_name);
+
+    // `--mode marathon|sprint|ultra|dig10|dig18|dig100|zen` picks the goal, the start menu can change it
+    let mut mode = arg_value(&args, "--mode")
+        .and_then(GameMode::from_name)
+        .unwrap_or_default();
+
+    // `--randomizer 7-bag|14-bag|uniform|nes|tgm` picks how pieces are dealt
+    overrides.randomizer = arg_value(&args, "--randomizer").and_then(RandomizerKind::from_name);
+
+    // `--messiness 0..100` is the chance a garbage hole changes column
+    overrides.messiness = arg_value(&args, "--messiness").and_then(|m| m.parse().ok());
+
+    // `--lock-delay 500` (milliseconds) and `--lock-resets 15` tune how long a
+    // grounded piece can be moved before it locks
+    overrides.lock_delay_ms = arg_value(&args, "--lock-delay").and_then(|ms| ms.parse().ok());
+    overrides.max_lock_resets =
+        arg_value(&args, "--lock-resets").and_then(|resets| resets.parse().ok());
+
+    // `--seed 1234` deals the same pieces every game
+    if let Some(seed) = arg_value(&args, "--seed").and_then(|seed| seed.parse().ok()) {
+        settings.seed = Some(seed);
+    }
+
+    // `--board 10x20` and `--buffer 20` resize the playfield, within the limits
+    // of `BoardSize`
+    let mut board = BoardSize::default();
+    if let Some((width, height)) = arg_value(&args, "--board").and_then(|size| size.split_once('x'))
+    {
+        board.width = width
+            .parse::<usize>()
+            .unwrap_or(board.width)
+            .clamp(BoardSize::MIN_SIDE, BoardSize::MAX_SIDE);
+        board.height = height
+            .parse::<usize>()
+            .unwrap_or(board.height)
+            .clamp(BoardSize::MIN_SIDE, BoardSize::MAX_SIDE);
+    }
+    if let Some(buffer) =
+        arg_value(&args, "--buffer").and_then(|buffer| buffer.parse::<usize>().ok())
+    {
+        board.buffer = buffer.min(BoardSize::MAX_BUFFER);
+    }
+
+    // `--das 167`, `--arr 33` (milliseconds) and `--sdf 20` tune held movement
+    overrides.das_ms = arg_value(&args, "--das").and_then(|das| das.parse().ok());
+    overrides.arr_ms = arg_value(&args, "--arr").and_then(|arr| arr.parse().ok());
+    overrides.soft_drop_factor = arg_value(&args, "--sdf").and_then(|sdf| sdf.parse().ok());
+
+    // A high score file that can't be read is moved aside rather than saved over
+    let (mut high_scores, scores_error) = match HighScores::load() {
+        Ok(high_scores) => (high_scores, None),
+        Err(err) => (HighScores::default(), Some(err)),
+    };
+    let backup = scores_error.as_ref().map(|_| HighScores::back_up());
+    let save_scores = !matches!(backup, Some(Err(_)));
     let mut terminal = ratatui::Terminal::new(CrosstermBackend::new(stdout()))?;
-    let mut game = Game::new();
-    let mut menu_selected = 0; // 0 for "Start Game", 1 for "Quit"
+
+    if let (Some(err), Some(backup)) = (&scores_error, &backup) {
+        let fate = match backup {
+            Ok(name) => format!("It was moved to {} and a new table started.", name),
+            Err(_) => "It couldn't be moved aside, so new results won't be saved.".to_string(),
+        };
+        show_message(
+            &mut terminal,
+            "HIGH SCORES NOT LOADED",
+            &format!("The high score file couldn't be read. {}\n\n{}", fate, err),
+        )?;
+    }
+    if let Some(err) = &settings_error {
+        show_message(
+            &mut terminal,
+            "SETTINGS NOT LOADED",
+            &format!(
+                "The config file couldn't be read, so the defaults are used and changes won't be saved until it is fixed.\n\n{}",
+                err
+            ),
+        )?;
+    }
+
+    // `--replay path/to/replay.toml` watches a saved replay before the start menu
+    if let Some(path) = arg_value(&args, "--replay") {
+        match Replay::load(Path::new(path)) {
+            Ok(replay) => run_replay(&mut terminal, &replay, &overrides.apply(&settings))?,
+            Err(err) => show_message(
+                &mut terminal,
+                "REPLAY NOT LOADED",
+                &format!("{} couldn't be read as a replay.\n\n{}", path, err),
+            )?,
+        }
+    }
+
+    let mut menu_selected = 0; // Index into START_MENU_ITEMS
     let mut game_running = false;
 
     // Show the start menu before the game begins
     while !game_running {
         terminal.draw(|frame| {
-            render_start_menu(frame); // Render the start menu
+            render_start_menu(frame, menu_selected, mode); // Render the start menu
         })?;
 
         if crossterm::event::poll(Duration::from_millis(50))? {
-            if let event::Event::Key(KeyEvent { code, .. }) = event::read()? {
+            if let event::Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            {
                 match code {
                     KeyCode::Down => {
-                        menu_selected = (menu_selected + 1) % 2; // Toggle between "Start Game" and "Quit"
+                        menu_selected = (menu_selected + 1) % START_MENU_ITEMS.len();
                     }
                     KeyCode::Up => {
-                        menu_selected = if menu_selected == 0 { 1 } else { 0 };
-                    }
-                    KeyCode::Enter => {
-                        if menu_selected == 0 {
-                            game_running = true; // Start the game
-                        } else {
-                            break; // Quit the game
-                        }
+                        menu_selected =
+                            (menu_selected + START_MENU_ITEMS.len() - 1) % START_MENU_ITEMS.len();
                     }
+                    // Left/Right or Enter on the mode row cycle through the modes
+                    KeyCode::Left if menu_selected == 1 => mode = cycle(&GameMode::ALL, mode, -1),
+                    KeyCode::Right if menu_selected == 1 => mode = cycle(&GameMode::ALL, mode, 1),
+                    KeyCode::Enter => match menu_selected {
+                        0 => game_running = true, // Start the game
+                        1 => mode = cycle(&GameMode::ALL, mode, 1),
+                        2 => run_leaderboard(
+                            &mut terminal,
+                            &high_scores,
+                            board,
+                            mode,
+                            &overrides.apply(&settings),
+                        )?,
+                        3 => run_replay_list(&mut terminal, &overrides.apply(&settings))?,
+                        4 => run_settings_menu(
+                            &mut terminal,
+                            &mut settings,
+                            settings_error.is_none(),
+                        )?,
+                        _ => break, // Quit the game
+                    },
                     KeyCode::Esc => {
                         break; // Quit on Esc key
                     }
@@ -53,45 +213,109 @@ fn main() -> io::Result<()> {
         }
     }
 
+    let settings = overrides.apply(&settings);
+    let bindings = settings.key_bindings();
+    let mut input = InputHandler::new(settings.input_config(), tracks_releases);
+    let mut game = new_game(board, mode, &settings);
+
     // Now start the game logic
     while game_running {
-        if crossterm::event::poll(Duration::from_millis(50))? {
-            if let event::Event::Key(KeyEvent {
-                code, modifiers: _, ..
-            }) = event::read()?
-            {
-                match code {
-                    KeyCode::Left => game.move_shape(-1, 0),
-                    KeyCode::Right => game.move_shape(1, 0),
-                    KeyCode::Down => game.move_shape(0, 1),
-                    KeyCode::Up => game.rotate_shape(),
-                    KeyCode::Char('p') => game.paused = !game.paused, // Toggle pause with the 'P' key
-                    KeyCode::Esc => break,                            // Exit the game with Esc
-                    _ => {}
+        // Poll often so auto repeat stays close to the configured ARR
+        if crossterm::event::poll(Duration::from_millis(10))? {
+            if let event::Event::Key(KeyEvent { code, kind, .. }) = event::read()? {
+                // While paused only Pause, Restart and Quit do anything
+                let action = bindings
+                    .action(code)
+                    .filter(|&action| !game.paused || !action.moves_piece());
+                match kind {
+                    KeyEventKind::Release => match action {
+                        Some(Action::MoveLeft) => input.release_shift(Shift::Left),
+                        Some(Action::MoveRight) => input.release_shift(Shift::Right),
+                        Some(Action::SoftDrop) => input.release_soft_drop(),
+                        _ => {}
+                    },
+                    // Held keys repeat through DAS/ARR rather than the terminal
+                    KeyEventKind::Repeat if input.tracks_releases => {}
+                    _ => {
+                        if action.is_some_and(Action::moves_piece) {
+                            game.stats.inputs += 1;
+                        }
+                        match action {
+                            Some(Action::MoveLeft) => {
+                                input.press_shift(Shift::Left, &mut game, Instant::now())
+                            }
+                            Some(Action::MoveRight) => {
+                                input.press_shift(Shift::Right, &mut game, Instant::now())
+                            }
+                            Some(Action::SoftDrop) => {
+                                input.press_soft_drop(&mut game, Instant::now())
+                            }
+                            Some(Action::HardDrop) => game.hard_drop(),
+                            Some(Action::RotateClockwise) => game.rotate_shape(Rotation::Clockwise),
+                            Some(Action::RotateCounterClockwise) => {
+                                game.rotate_shape(Rotation::CounterClockwise)
+                            }
+                            Some(Action::Rotate180) => game.rotate_shape(Rotation::Half),
+                            Some(Action::Hold) => game.hold_shape(),
+                            Some(Action::Pause) => {
+                                game.set_paused(!game.paused);
+                                input.clear();
+                            }
+                            Some(Action::Restart) => {
+                                save_replay(&game, &settings);
+                                game = new_game(board, mode, &settings);
+                                input.clear();
+                            }
+                            Some(Action::Quit) => {
+                                save_replay(&game, &settings);
+                                break;
+                            }
+                            None => {}
+                        }
+                    }
                 }
             }
         }
 
         // If the game is not paused, continue the game logic
-        if !game.paused && game.last_tick.elapsed() > game.tick_rate {
-            game.last_tick = Instant::now();
-            game.move_shape(0, 1); // Move the shape down over time
+        if !game.paused {
+            input.update(&mut game, Instant::now());
+            game.tick();
         }
 
         if game.is_game_over() {
-            // Show the Game Over screen
-            terminal.draw(|frame| {
-                render_gameover_menu(frame);
-            })?;
+            // Games with a chosen seed aren't ranked, the pieces were known in advance
+            let rank = match settings.seed {
+                Some(_) => None,
+                None => high_scores.record(&game, &settings.name),
+            };
+            if rank.is_some() && save_scores {
+                let _ = high_scores.save();
+            }
+            save_replay(&game, &settings);
 
-            // Wait for user input to either restart or quit
-            while let crossterm::event::Event::Key(KeyEvent { code, .. }) = event::read()? {
-                match code {
-                    KeyCode::Char('r') => {
-                        game = Game::new(); // Restart the game
+            // Show the results screen until the player restarts or quits. Other
+            // events, like a resize, just redraw it
+            loop {
+                terminal.draw(|frame| {
+                    render_gameover_menu(frame, &game, rank, &bindings);
+                })?;
+
+                let event::Event::Key(KeyEvent {
+                    code,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) = event::read()?
+                else {
+                    continue;
+                };
+                match bindings.action(code) {
+                    Some(Action::Restart) => {
+                        game = new_game(board, mode, &settings); // Restart the game
+                        input.clear();
                         break; // Exit the game over loop
                     }
-                    KeyCode::Char('q') => {
+                    Some(Action::Quit) => {
                         game_running = false; // Quit the game
                         break; // Exit the game over loop
                     }
@@ -99,11 +323,219 @@ fn main() -> io::Result<()> {
                 }
             }
         } else {
-            render(&mut terminal, &game)?; // Render the game state (either paused or running)
+            render(&mut terminal, &game, &settings, &bindings)?; // Render the game state (either paused or running)
         }
     }
 
+    if tracks_releases {
+        execute!(stdout(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(stdout(), LeaveAlternateScreen)?;
     Ok(())
 }
+
+/// Start a game with the chosen seed, or a fresh random one when none is set
+fn new_game(board: BoardSize, mode: GameMode, settings: &Settings) -> Game {
+    let seed = settings.seed.unwrap_or_else(rand::random);
+    Game::new(board, mode, settings.ruleset(), seed)
+}
+
+/// Save the game's replay, unless nothing was played. Saving here, like saving
+/// the high scores and settings, is best effort: a full disk or read-only data
+/// directory shouldn't end a session, and what wasn't saved is still shown
+/// until the game closes
+fn save_replay(game: &Game, settings: &Settings) {
+    if !game.input_log.is_empty() {
+        let _ = Replay::from_game(game, &settings.name).save();
+    }
+}
+
+/// Show the saved replays, newest first, until the player leaves
+fn run_replay_list(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    settings: &Settings,
+) -> io::Result<()> {
+    let replays = replays::list();
+    let mut selected = 0;
+
+    loop {
+        terminal.draw(|frame| render_replay_list(frame, &replays, selected))?;
+
+        if let event::Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        {
+            match code {
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected = (selected + 1).min(replays.len().saturating_sub(1)),
+                KeyCode::Enter => {
+                    if let Some(replay) = replays.get(selected) {
+                        run_replay(terminal, replay, settings)?;
+                    }
+                }
+                KeyCode::Esc => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Play a replay back until the player leaves. Space pauses, Left/Right seek,
+/// Up/Down change the speed and `.` steps one frame at a time
+fn run_replay(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    replay: &Replay,
+    settings: &Settings,
+) -> io::Result<()> {
+    // Replays from other versions can't be played faithfully
+    let mut player = match replay.player() {
+        Ok(player) => player,
+        Err(reason) => {
+            return show_message(
+                terminal,
+                "REPLAY CAN'T BE PLAYED",
+                &format!("This replay can't be played back. {}", reason),
+            )
+        }
+    };
+    let mut last_frame = Instant::now();
+
+    loop {
+        render_replay(terminal, &player, replay, settings)?;
+
+        if crossterm::event::poll(Duration::from_millis(10))? {
+            if let event::Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            {
+                match code {
+                    // Playing an ended replay starts it over
+                    KeyCode::Char(' ') if player.is_finished() => {
+                        player.seek(Duration::ZERO);
+                        player.paused = false;
+                    }
+                    KeyCode::Char(' ') => player.paused = !player.paused,
+                    KeyCode::Left => player.seek(player.position.saturating_sub(REPLAY_SEEK_STEP)),
+                    KeyCode::Right => player.seek(player.position + REPLAY_SEEK_STEP),
+                    KeyCode::Up => player.faster(),
+                    KeyCode::Down => player.slower(),
+                    KeyCode::Char('.') => player.step(),
+                    KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+
+        let now = Instant::now();
+        player.update(now.duration_since(last_frame));
+        last_frame = now;
+    }
+}
+
+/// Show the high score tables, starting at `mode`, until the player leaves
+fn run_leaderboard(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    high_scores: &HighScores,
+    board: BoardSize,
+    mode: GameMode,
+    settings: &Settings,
+) -> io::Result<()> {
+    let ruleset = settings.ruleset();
+    let modes: Vec<GameMode> = GameMode::ALL
+        .into_iter()
+        .filter(|mode| *mode != GameMode::Zen)
+        .collect();
+    let mut mode = if modes.contains(&mode) {
+        mode
+    } else {
+        modes[0]
+    };
+
+    loop {
+        terminal.draw(|frame| {
+            render_leaderboard(
+                frame,
+                mode,
+                board,
+                &ruleset,
+                high_scores.entries(mode, board, &ruleset),
+            )
+        })?;
+
+        if let event::Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        {
+            match code {
+                KeyCode::Left => mode = cycle(&modes, mode, -1),
+                KeyCode::Right => mode = cycle(&modes, mode, 1),
+                KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Show the settings screen until the player leaves it, then save the settings
+/// unless `save` is off
+fn run_settings_menu(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    settings: &mut Settings,
+    save: bool,
+) -> io::Result<()> {
+    let mut menu = SettingsMenu::default();
+
+    loop {
+        terminal.draw(|frame| render_settings_menu(frame, &menu, settings))?;
+
+        if let event::Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        {
+            if !menu.handle_key(code, settings) {
+                break;
+            }
+        }
+    }
+
+    if save {
+        let _ = settings.save();
+    }
+    Ok(())
+}
+
+/// Show a message until any key is pressed
+fn show_message(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    title: &str,
+    message: &str,
+) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| render_message(frame, title, message))?;
+
+        if let event::Event::Key(KeyEvent {
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Return the value following `flag` on the command line
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
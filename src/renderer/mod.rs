@@ -1,21 +1,53 @@
-use crate::game::control::{COLS, ROWS};
+pub mod theme;
+
+use crate::game::control::{BoardSize, Cell, Ruleset, NEXT_QUEUE_SIZE};
+use crate::game::mode::{format_time, GameMode};
+use crate::game::replay::ReplayPlayer;
+use crate::game::shape::Shape;
 use crate::game::Game;
+use crate::keys::{Action, KeyBindings};
+use crate::replays::Replay;
+use crate::scores::{board_name, format_date, ScoreEntry};
+use crate::settings::{Settings, SettingsMenu};
 use ratatui::{
     layout::{Alignment, Rect},
     prelude::CrosstermBackend,
-    style::{Color, Style},
-    widgets::{Block, Borders, Paragraph},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame, Terminal,
 };
 use std::io::Result;
+use theme::Theme;
+
+/// Rows taken by the hold box, borders included
+const HOLD_PREVIEW_HEIGHT: u16 = 7;
 
-pub fn render_start_menu(frame: &mut ratatui::Frame) {
-    let menu_items = ["Start Game", "Quit"];
+pub const START_MENU_ITEMS: [&str; 6] = [
+    "Start Game",
+    "Mode",
+    "High Scores",
+    "Replays",
+    "Settings",
+    "Quit",
+];
+
+pub fn render_start_menu(frame: &mut ratatui::Frame, selected: usize, mode: GameMode) {
     let mut menu_output = String::new();
 
-    for (i, item) in menu_items.iter().enumerate() {
-        menu_output.push_str(item);
-        if i != menu_items.len() - 1 {
+    for (i, &item) in START_MENU_ITEMS.iter().enumerate() {
+        // The mode row shows the picked mode, Left/Right change it
+        let item = if item == "Mode" {
+            format!("< Mode: {} >", mode.title())
+        } else {
+            item.to_string()
+        };
+        if i == selected {
+            menu_output.push_str(&format!("> {} <", item)); // Mark the selected item
+        } else {
+            menu_output.push_str(&item);
+        }
+        if i != START_MENU_ITEMS.len() - 1 {
             menu_output.push('\n'); // Add newline between menu items
         }
     }
@@ -42,20 +74,220 @@ pub fn render_start_menu(frame: &mut ratatui::Frame) {
     frame.render_widget(paragraph, area);
 }
 
-pub fn render_gameover_menu(frame: &mut ratatui::Frame) {
-    let gameover_message = "Game Over\n\nPress 'R' to Restart or 'Q' to Quit";
+/// Show how the game ended along with the mode's results
+pub fn render_gameover_menu(
+    frame: &mut ratatui::Frame,
+    game: &Game,
+    rank: Option<usize>,
+    bindings: &KeyBindings,
+) {
+    let (title, headline, color) = match game.game_over_reason() {
+        Some(reason) => ("GAME OVER", reason.description(), Color::Red),
+        None => ("COMPLETE", "Goal reached", Color::Green),
+    };
+
+    let mut summary = game.mode.summary(game);
+    if let Some(rank) = rank {
+        summary.push(format!("New high score: #{}", rank + 1));
+    }
+
+    let gameover_message = format!(
+        "{}\n\n{}\n\n{}\n\n{}\n\nSeed: {}\n\nPress '{}' to Restart or '{}' to Quit",
+        game.mode.title(),
+        headline,
+        summary.join("\n"),
+        statistics(game).join("\n"),
+        game.seed,
+        bindings.describe(Action::Restart),
+        bindings.describe(Action::Quit)
+    );
 
     let paragraph = Paragraph::new(gameover_message)
         .block(
             Block::default()
-                .title("GAME OVER")
+                .title(title)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red))
+                .border_style(Style::default().fg(color))
                 .style(Style::default().bg(Color::Black).fg(Color::White)),
         )
         .alignment(Alignment::Center) // Center the game over message
         .style(Style::default().fg(Color::Yellow));
 
+    let size = frame.area();
+    let area = Rect::new(
+        size.width / 4,
+        size.height / 8,
+        size.width / 2,
+        size.height * 3 / 4,
+    );
+
+    frame.render_widget(paragraph, area);
+}
+
+/// Per-game statistics as aligned label and value columns
+fn statistics(game: &Game) -> Vec<String> {
+    let stats = &game.stats;
+    let [singles, doubles, triples, tetrises] = stats.clears;
+    let rows = [
+        ("Time", format_time(game.time)),
+        (
+            "Pieces",
+            format!(
+                "{} ({:.2} PPS)",
+                stats.pieces,
+                stats.pieces_per_second(game.time)
+            ),
+        ),
+        (
+            "Inputs per piece",
+            format!("{:.2}", stats.inputs_per_piece()),
+        ),
+        ("Singles", singles.to_string()),
+        ("Doubles", doubles.to_string()),
+        ("Triples", triples.to_string()),
+        ("Tetrises", tetrises.to_string()),
+        ("T-spins", stats.tspins.to_string()),
+        ("Max combo", stats.max_combo.to_string()),
+        ("Max back-to-back", stats.max_back_to_back.to_string()),
+        (
+            "Attack",
+            format!(
+                "{} ({:.1} APM)",
+                stats.attack,
+                stats.attack_per_minute(game.time)
+            ),
+        ),
+    ];
+
+    rows.iter()
+        .map(|(label, value)| format!("{:<18}{:>16}", label, value))
+        .collect()
+}
+
+/// Show the high score table of one mode under one ruleset
+pub fn render_leaderboard(
+    frame: &mut ratatui::Frame,
+    mode: GameMode,
+    board: BoardSize,
+    ruleset: &Ruleset,
+    entries: &[ScoreEntry],
+) {
+    let mut header = format!("< {} >   rules: {}", mode.title(), ruleset.name());
+    if board != BoardSize::default() {
+        header.push_str(&format!("   board: {}", board_name(board)));
+    }
+    let mut lines = vec![
+        Line::raw(header),
+        Line::raw(""),
+        Line::styled(
+            format!(
+                "{:>3}  {:<16}{:>9}{:>7}{:>5}{:>11}{:>6}  {}",
+                "#", "Name", "Score", "Lines", "Lvl", "Time", "PPS", "Date"
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+    ];
+
+    for (i, entry) in entries.iter().enumerate() {
+        lines.push(Line::raw(format!(
+            "{:>3}  {:<16}{:>9}{:>7}{:>5}{:>11}{:>6.2}  {}",
+            i + 1,
+            entry.name,
+            entry.score,
+            entry.lines,
+            entry.level,
+            format_time(entry.time()),
+            entry.pps,
+            format_date(entry.date)
+        )));
+    }
+    if entries.is_empty() {
+        lines.push(Line::raw("  No results yet"));
+    }
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title("HIGH SCORES - Left/Right to change mode, Esc to go back")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta))
+            .style(Style::default().bg(Color::Black).fg(Color::White)),
+    );
+
+    let size = frame.area();
+    let area = Rect::new(
+        size.width / 8,
+        size.height / 8,
+        size.width * 3 / 4,
+        size.height * 3 / 4,
+    );
+
+    frame.render_widget(paragraph, area);
+}
+
+pub fn render_replay_list(frame: &mut ratatui::Frame, replays: &[Replay], selected: usize) {
+    let mut lines = vec![Line::styled(
+        format!(
+            "  {:<12}{:<12}{:<16}{:>9}{:>7}{:>11}",
+            "Date", "Mode", "Name", "Score", "Lines", "Time"
+        ),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+
+    for (i, replay) in replays.iter().enumerate() {
+        let text = format!(
+            "{:<12}{:<12}{:<16}{:>9}{:>7}{:>11}",
+            format_date(replay.date),
+            GameMode::from_name(&replay.mode).map_or(replay.mode.clone(), GameMode::title),
+            replay.name,
+            replay.score,
+            replay.lines,
+            format_time(replay.time())
+        );
+        if i == selected {
+            lines.push(Line::styled(
+                format!("> {}", text),
+                Style::default().fg(Color::Yellow),
+            ));
+        } else {
+            lines.push(Line::raw(format!("  {}", text)));
+        }
+    }
+    if replays.is_empty() {
+        lines.push(Line::raw("  No replays yet"));
+    }
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title("REPLAYS - Enter to watch, Esc to go back")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta))
+            .style(Style::default().bg(Color::Black).fg(Color::White)),
+    );
+
+    let size = frame.area();
+    let area = Rect::new(
+        size.width / 8,
+        size.height / 8,
+        size.width * 3 / 4,
+        size.height * 3 / 4,
+    );
+
+    frame.render_widget(paragraph, area);
+}
+
+/// A message box, e.g. to explain why something couldn't be loaded
+pub fn render_message(frame: &mut ratatui::Frame, title: &str, message: &str) {
+    let paragraph = Paragraph::new(format!("{}\n\nPress any key to continue", message))
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Red))
+                .style(Style::default().bg(Color::Black).fg(Color::White)),
+        )
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
     let size = frame.area();
     let area = Rect::new(
         size.width / 4,
@@ -64,34 +296,124 @@ pub fn render_gameover_menu(frame: &mut ratatui::Frame) {
         size.height / 2,
     );
 
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+pub fn render_settings_menu(frame: &mut ratatui::Frame, menu: &SettingsMenu, settings: &Settings) {
+    let lines: Vec<Line> = menu
+        .rows(settings)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let value = if i == menu.selected && menu.rebinding {
+                "press a key...".to_string()
+            } else {
+                value
+            };
+            let text = format!("{:<24}{}", label, value);
+            if i == menu.selected {
+                Line::styled(format!("> {}", text), Style::default().fg(Color::Yellow))
+            } else {
+                Line::raw(format!("  {}", text))
+            }
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title("SETTINGS - Left/Right to change, Enter to rebind, type a name or seed, Esc to save")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta))
+            .style(Style::default().bg(Color::Black).fg(Color::White)),
+    );
+
+    let size = frame.area();
+    let area = Rect::new(
+        size.width / 8,
+        size.height / 8,
+        size.width * 3 / 4,
+        size.height * 3 / 4,
+    );
+
     frame.render_widget(paragraph, area);
 }
 
 pub fn render(
     terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
     game: &Game,
+    settings: &Settings,
+    bindings: &KeyBindings,
 ) -> Result<()> {
     terminal
         .draw(|frame| {
             if game.paused {
-                render_pause_menu(frame);
+                render_pause_menu(frame, bindings);
             } else {
-                // Render the main game area
-                render_game_area(frame, game);
+                render_playfield(frame, game, settings);
+            }
+        })
+        .map(|_| ())
+}
 
-                // Render the next shape preview
-                render_next_shape_preview(frame, game);
+/// Draw a replay being played back, with the playback controls along the bottom
+pub fn render_replay(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    player: &ReplayPlayer,
+    replay: &Replay,
+    settings: &Settings,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            render_playfield(frame, &player.game, settings);
 
-                // Render the current falling shape with its unique color
-                render_falling_shape(frame, game);
-            }
+            let state = if player.is_finished() {
+                "ended"
+            } else if player.paused {
+                "paused"
+            } else {
+                "playing"
+            };
+            let status = format!(
+                " REPLAY {} by {}  {} / {}  {}x  {}   Space play/pause  Left/Right seek  Up/Down speed  . step  Esc back",
+                replay.mode,
+                replay.name,
+                format_time(player.position),
+                format_time(player.length),
+                player.speed(),
+                state
+            );
+            let size = frame.area();
+            let area = Rect::new(0, size.height.saturating_sub(1), size.width, 1.min(size.height));
+            frame.render_widget(
+                Paragraph::new(status).style(Style::default().bg(Color::Magenta).fg(Color::Black)),
+                area,
+            );
         })
         .map(|_| ())
 }
 
-pub fn render_pause_menu(frame: &mut Frame) {
-    // Displaying a static message as the pause prompt
-    let pause_message = "Game Paused\n\nPress 'P' to resume";
+/// Draw the board with the falling shape and the side panels
+fn render_playfield(frame: &mut ratatui::Frame, game: &Game, settings: &Settings) {
+    // Render the main game area along with the falling shape
+    render_game_area(frame, game, settings);
+
+    // Render the side panels last so the board doesn't draw over them
+    let queue_height = render_next_queue(frame, game, settings);
+    render_hold_preview(frame, game, settings.theme, queue_height);
+    render_progress(
+        frame,
+        game,
+        settings.theme,
+        queue_height + HOLD_PREVIEW_HEIGHT,
+    );
+}
+
+pub fn render_pause_menu(frame: &mut Frame, bindings: &KeyBindings) {
+    let pause_message = format!(
+        "Game Paused\n\nPress '{}' to resume",
+        bindings.describe(Action::Pause)
+    );
 
     // Example ASCII Art for the pause menu
     let ascii_art = r#"
@@ -152,135 +474,192 @@ pub fn render_pause_menu(frame: &mut Frame) {
     // Render the paragraph with centered text and a bordered block
     frame.render_widget(paragraph, area);
 }
-/// Render the main game area (game board)
-
-fn precompute_active_cells(game: &Game) -> Vec<Vec<bool>> {
-    let mut active_cells = vec![vec![false; COLS]; ROWS];
-
-    // Mark the active cells of the shape on the game board
-    for x in 0..game.current.width {
-        for y in 0..game.current.width {
-            if game.current.array[x][y] == 1 {
-                let shape_row = game.current.row + x as isize;
-                let shape_col = game.current.col + y as isize;
-
-                if shape_row >= 0
-                    && shape_col >= 0
-                    && shape_row < ROWS as isize
-                    && shape_col < COLS as isize
-                {
-                    active_cells[shape_row as usize][shape_col as usize] = true;
-                }
-            }
+/// Render the main game area (game board). Locked cells keep the color of the
+/// piece that left them, the falling shape uses its own color and the ghost is dimmed
+fn render_game_area(frame: &mut ratatui::Frame, game: &Game, settings: &Settings) {
+    let theme = settings.theme;
+    let current_cells = shape_cells(game.board, &game.current);
+    let ghost_cells = if settings.ghost {
+        shape_cells(game.board, &game.ghost_shape())
+    } else {
+        Vec::new()
+    };
+
+    let current_style = Style::default().fg(theme.piece_color(game.current.kind));
+    let ghost_style = current_style.add_modifier(Modifier::DIM);
+
+    // Only the visible rows are drawn, the hidden buffer above them stays off screen
+    let mut lines = Vec::with_capacity(game.board.height + 8);
+    for (i, row) in game.table.iter().enumerate().skip(game.board.buffer) {
+        let mut spans = Vec::with_capacity(game.board.width);
+        for (j, cell) in row.iter().enumerate() {
+            let span = if current_cells.contains(&(i, j)) {
+                Span::styled("O ", current_style)
+            } else if let Cell::Filled(kind) = cell {
+                Span::styled("O ", Style::default().fg(theme.piece_color(*kind)))
+            } else if *cell == Cell::Garbage {
+                Span::styled("O ", Style::default().fg(theme.garbage_color()))
+            } else if ghost_cells.contains(&(i, j)) {
+                Span::styled("+ ", ghost_style)
+            } else {
+                Span::raw(". ") // Empty space
+            };
+            spans.push(span);
         }
+        lines.push(Line::from(spans));
     }
 
-    active_cells
-}
-
-fn render_game_area(frame: &mut ratatui::Frame, game: &Game) {
-    let active_cells = precompute_active_cells(game);
+    let mut output = Vec::with_capacity(64);
 
-    let mut output = Vec::with_capacity((20 * 11 * 2) + 20); // Preallocate memory
-
-    for i in 0..20 {
-        for j in 0..11 {
-            let mut cell = b'.'; // Default to empty space
+    // Level, lines and what is left to dig sit in the side panel
+    output.extend_from_slice(format!("\nScore: {}\n", game.score).as_bytes());
 
-            // Check if the cell is occupied by the active shape or the game board
-            if game.table[i][j] == 1 || active_cells[i][j] {
-                cell = b'O'; // Active cell in the game or active shape cell
-            }
+    // Progress towards the mode's goal
+    output.extend_from_slice(format!("\n{}\n", game.mode.title()).as_bytes());
+    for line in game.mode.status(game) {
+        output.extend_from_slice(format!("{}\n", line).as_bytes());
+    }
 
-            output.push(cell);
-            output.push(b' '); // Space for readability
+    // Announce the last scoring clear for a short while
+    if let Some(clear) = game
+        .last_clear
+        .as_ref()
+        .filter(|clear| clear.is_visible(game.time))
+    {
+        output.extend_from_slice(format!("\n{}\n", clear.label()).as_bytes());
+        if clear.back_to_back {
+            output.extend_from_slice(b"Back-to-Back\n");
+        }
+        if clear.combo > 0 {
+            output.extend_from_slice(format!("{} Combo\n", clear.combo).as_bytes());
         }
-        output.push(b'\n');
     }
 
-    // Append score using efficient formatting
-    output.extend_from_slice(format!("\nScore: {}\n", game.score).as_bytes());
-
     // Convert Vec<u8> to String once
     let output_str = String::from_utf8(output).unwrap();
+    lines.extend(output_str.lines().map(|line| Line::raw(line.to_string())));
 
-    let paragraph = Paragraph::new(output_str)
+    let paragraph = Paragraph::new(lines)
         .block(Block::default().borders(Borders::ALL))
         .style(Style::default().fg(Color::White));
 
     frame.render_widget(paragraph, frame.area());
 }
 
-/// Render the preview of the next shape
+/// Render the upcoming shapes stacked in one box, returning the box's height
+fn render_next_queue(frame: &mut ratatui::Frame, game: &Game, settings: &Settings) -> u16 {
+    let count = settings.next_queue.clamp(1, NEXT_QUEUE_SIZE);
+    let mut lines = vec![Line::raw(""), Line::raw("Next:")];
+
+    for shape in game.next_queue.iter().take(count) {
+        let style = Style::default().fg(settings.theme.piece_color(shape.kind));
+        // Skip the empty rows of the bounding box to keep the queue compact
+        for row in shape.array.iter().filter(|row| row.contains(&1)) {
+            let spans: Vec<Span> = row
+                .iter()
+                .map(|&value| match value {
+                    1 => Span::styled("O ", style),
+                    _ => Span::raw("  "),
+                })
+                .collect();
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::raw(""));
+    }
+
+    let height = lines.len() as u16 + 2; // Borders
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::White));
+
+    let area =
+        Rect::new(frame.area().width.saturating_sub(20), 0, 20, height).intersection(frame.area());
+    frame.render_widget(paragraph, area);
+    height
+}
 
-fn render_next_shape_preview(frame: &mut ratatui::Frame, game: &Game) {
-    let mut output = Vec::with_capacity(game.next_shape.width * game.next_shape.width * 2 + 15);
+/// Render level, lines and the garbage left to dig below the hold slot
+fn render_progress(frame: &mut ratatui::Frame, game: &Game, theme: Theme, y: u16) {
+    let mut lines = vec![
+        Line::raw(format!("Level: {}", game.level)),
+        Line::raw(format!("Lines: {}", game.lines)),
+    ];
+    if let Some(left) = game.mode.garbage_left(game) {
+        lines.push(Line::styled(
+            format!("Garbage: {}", left),
+            Style::default().fg(theme.garbage_color()),
+        ));
+    }
 
-    output.extend_from_slice(b"\nNext Shape:\n");
+    let height = lines.len() as u16 + 2; // Borders
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::White));
 
-    for i in 0..game.next_shape.width {
-        for j in 0..game.next_shape.width {
-            let cell = if game.next_shape.array[i][j] == 1 {
-                b'O'
-            } else {
-                b'.'
-            };
-            output.push(cell);
-            output.push(b' '); // Add space for spacing
+    let area =
+        Rect::new(frame.area().width.saturating_sub(20), y, 20, height).intersection(frame.area());
+    frame.render_widget(paragraph, area);
+}
+
+/// Render the hold slot below the next queue
+fn render_hold_preview(frame: &mut ratatui::Frame, game: &Game, theme: Theme, y: u16) {
+    render_shape_preview(frame, "Hold", game.hold.as_ref(), y, theme);
+}
+
+fn render_shape_preview(
+    frame: &mut ratatui::Frame,
+    title: &str,
+    shape: Option<&Shape>,
+    y: u16,
+    theme: Theme,
+) {
+    let width = shape.map_or(0, |shape| shape.width);
+    let mut output = Vec::with_capacity(width * width * 2 + title.len() + 3);
+
+    output.extend_from_slice(format!("\n{}:\n", title).as_bytes());
+
+    if let Some(shape) = shape {
+        for row in &shape.array {
+            for &value in row {
+                let cell = if value == 1 { b'O' } else { b'.' };
+                output.push(cell);
+                output.push(b' '); // Add space for spacing
+            }
+            output.push(b'\n');
         }
-        output.push(b'\n');
     }
 
     let preview_output = String::from_utf8(output).unwrap(); // Convert once
 
+    let color = shape.map_or(Color::White, |shape| theme.piece_color(shape.kind));
     let preview_paragraph = Paragraph::new(preview_output)
         .block(Block::default().borders(Borders::ALL))
-        .style(Style::default().fg(Color::White));
+        .style(Style::default().fg(color));
 
     let preview_area = ratatui::layout::Rect::new(
         frame.area().width.saturating_sub(20), // Avoid negative overflow
-        0,
+        y,
         20,
-        7,
-    );
+        HOLD_PREVIEW_HEIGHT,
+    )
+    .intersection(frame.area()); // The hold box sits below the queue and may run off screen
 
     frame.render_widget(preview_paragraph, preview_area);
 }
 
-/// Render the current falling shape with its unique color
-fn render_falling_shape(frame: &mut ratatui::Frame, game: &Game) {
-    let mut output = Vec::with_capacity(20 * 11 * 2);
-
-    let mut shape_cells = vec![];
-    for x in 0..game.current.width {
-        for y in 0..game.current.width {
-            if game.current.array[x][y] == 1 {
-                let shape_row = (game.current.row + x as isize) as usize;
-                let shape_col = (game.current.col + y as isize) as usize;
-                if shape_row < 20 && shape_col < 11 {
-                    shape_cells.push((shape_row, shape_col));
+/// Collect the board cells covered by a shape that fall inside the playfield
+fn shape_cells(board: BoardSize, shape: &Shape) -> Vec<(usize, usize)> {
+    let mut cells = vec![];
+    for x in 0..shape.width {
+        for y in 0..shape.width {
+            if shape.array[x][y] == 1 {
+                let shape_row = (shape.row + x as isize) as usize;
+                let shape_col = (shape.col + y as isize) as usize;
+                if shape_row < board.rows() && shape_col < board.width {
+                    cells.push((shape_row, shape_col));
                 }
             }
         }
     }
-
-    for i in 0..20 {
-        for j in 0..11 {
-            let mut cell = if game.table[i][j] == 1 { b'O' } else { b'.' };
-            if shape_cells.contains(&(i, j)) {
-                cell = b'O';
-            }
-            output.push(cell);
-            output.push(b' ');
-        }
-        output.push(b'\n');
-    }
-
-    let output_with_color = String::from_utf8(output).unwrap();
-
-    let game_area_paragraph = Paragraph::new(output_with_color)
-        .block(Block::default().borders(Borders::ALL))
-        .style(Style::default().fg(game.current.color.into()));
-
-    frame.render_widget(game_area_paragraph, frame.area());
+    cells
 }
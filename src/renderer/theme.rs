@@ -0,0 +1,51 @@
+use crate::game::shape::PieceKind;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+/// Color scheme for the pieces on the board and in the side panels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// The colors tetoris has always used
+    #[default]
+    Classic,
+    /// Standard guideline colors: cyan I, yellow O, purple T and so on
+    Guideline,
+    Monochrome,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Classic, Theme::Guideline, Theme::Monochrome];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::Guideline => "guideline",
+            Theme::Monochrome => "monochrome",
+        }
+    }
+
+    pub fn piece_color(self, kind: PieceKind) -> Color {
+        match self {
+            Theme::Classic => kind.color().into(),
+            Theme::Guideline => match kind {
+                PieceKind::I => Color::Cyan,
+                PieceKind::O => Color::Yellow,
+                PieceKind::T => Color::Magenta,
+                PieceKind::S => Color::Green,
+                PieceKind::Z => Color::Red,
+                PieceKind::J => Color::Blue,
+                PieceKind::L => Color::Rgb(255, 165, 0),
+            },
+            Theme::Monochrome => Color::White,
+        }
+    }
+
+    /// Garbage stays gray so it stands apart from the pieces in every theme
+    pub fn garbage_color(self) -> Color {
+        match self {
+            Theme::Monochrome => Color::DarkGray,
+            Theme::Classic | Theme::Guideline => Color::Gray,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::config;
+use crate::game::control::{BoardSize, Ruleset};
+use crate::game::mode::GameMode;
+use crate::game::replay::{decode_inputs, encode_inputs, ReplayPlayer};
+use crate::game::Game;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Subdirectory of the data directory holding one file per replay
+const REPLAY_DIR: &str = "replays";
+
+/// Bumped whenever a change to the game would make old replays play differently
+const FORMAT_VERSION: u32 = 3;
+
+/// Everything needed to play a game again: the seed and rules decide every
+/// piece and garbage row, the timestamped inputs decide the rest
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub name: String,
+    pub date: u64, // Seconds since the Unix epoch
+    pub mode: String,
+    pub ruleset: Ruleset,
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub buffer: usize,
+    pub score: u32,
+    pub lines: u32,
+    pub time_ms: u64,
+    /// Input log, see `encode_inputs`
+    pub inputs: String,
+}
+
+impl Replay {
+    pub fn from_game(game: &Game, name: &str) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            name: name.to_string(),
+            date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            mode: game.mode.name(),
+            ruleset: game.ruleset,
+            seed: game.seed,
+            width: game.board.width,
+            height: game.board.height,
+            buffer: game.board.buffer,
+            score: game.score,
+            lines: game.lines,
+            time_ms: game.time.as_millis() as u64,
+            inputs: encode_inputs(&game.input_log),
+        }
+    }
+
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms)
+    }
+
+    /// Set up playback. Fails with the reason when the replay comes from an
+    /// incompatible version or is damaged
+    pub fn player(&self) -> Result<ReplayPlayer, &'static str> {
+        if self.version != FORMAT_VERSION {
+            return Err("It was recorded by a different version of the game.");
+        }
+        let board = BoardSize {
+            width: self.width,
+            height: self.height,
+            buffer: self.buffer,
+        };
+        if !board.is_supported() {
+            return Err("Its board size is out of range.");
+        }
+        let mode = GameMode::from_name(&self.mode).ok_or("Its game mode is unknown.")?;
+        let inputs = decode_inputs(&self.inputs).ok_or("Its inputs are damaged.")?;
+        Ok(ReplayPlayer::new(
+            board,
+            mode,
+            self.ruleset,
+            self.seed,
+            inputs,
+            self.time(),
+        ))
+    }
+
+    /// Save the replay to its own file in the replay directory. Games saved in
+    /// the same second get a counter, e.g. `1700000000-sprint-42-2.toml`
+    pub fn save(&self) -> io::Result<()> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let name = format!("{}/{}-{}-{}", REPLAY_DIR, self.date, self.mode, self.seed);
+        for count in 1.. {
+            let file = match count {
+                1 => format!("{}.toml", name),
+                _ => format!("{}-{}.toml", name, count),
+            };
+            match config::create_data(&file, &contents) {
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                result => return result,
+            }
+        }
+        unreachable!("a free file name is always found")
+    }
+
+    /// Read a replay file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Every saved replay, newest first. Unreadable files are skipped
+pub fn list() -> Vec<Replay> {
+    let Some(entries) = config::data_dir().and_then(|dir| fs::read_dir(dir.join(REPLAY_DIR)).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut replays: Vec<Replay> = entries
+        .filter_map(|entry| Replay::load(&entry.ok()?.path()).ok())
+        .collect();
+    replays.sort_by_key(|replay| std::cmp::Reverse(replay.date));
+    replays
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::shape::Rotation;
+
+    #[test]
+    fn replay_files_play_back_the_game() {
+        let board = BoardSize {
+            width: 8,
+            height: 16,
+            buffer: 4,
+        };
+        let mut game = Game::new(board, GameMode::Dig(10), Ruleset::default(), 99);
+        for step in 0..60u64 {
+            game.advance(Duration::from_millis(step * 250));
+            game.rotate_shape(Rotation::CounterClockwise);
+            game.shift(if step % 2 == 0 { -1 } else { 1 });
+            game.hard_drop();
+        }
+
+        let contents = toml::to_string_pretty(&Replay::from_game(&game, "a")).unwrap();
+        let replay: Replay = toml::from_str(&contents).unwrap();
+        let mut player = replay.player().unwrap();
+        player.seek(player.length);
+
+        assert_eq!(player.game.board, board);
+        assert_eq!(player.game.table, game.table);
+        assert_eq!(player.game.garbage_cleared, game.garbage_cleared);
+    }
+
+    #[test]
+    fn newer_replays_are_not_played() {
+        let game = Game::new(BoardSize::default(), GameMode::Zen, Ruleset::default(), 1);
+        let mut replay = Replay::from_game(&game, "a");
+        replay.version += 1;
+        assert!(replay.player().is_err());
+    }
+
+    #[test]
+    fn damaged_replays_are_not_played() {
+        let game = Game::new(BoardSize::default(), GameMode::Zen, Ruleset::default(), 1);
+        let mut replay = Replay::from_game(&game, "a");
+        replay.inputs = "12x".to_string();
+        assert_eq!(replay.player().err(), Some("Its inputs are damaged."));
+
+        let mut replay = Replay::from_game(&game, "a");
+        replay.mode = "tetris99".to_string();
+        assert_eq!(replay.player().err(), Some("Its game mode is unknown."));
+
+        for (height, buffer) in [(0, 0), (1_000_000, 20), (20, 1_000_000)] {
+            let mut replay = Replay::from_game(&game, "a");
+            replay.mode = "dig10".to_string();
+            (replay.height, replay.buffer) = (height, buffer);
+            assert_eq!(
+                replay.player().err(),
+                Some("Its board size is out of range.")
+            );
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use crate::config;
+use crate::game::control::{BoardSize, Ruleset};
+use crate::game::mode::GameMode;
+use crate::game::Game;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Entries kept per mode and ruleset
+pub const MAX_ENTRIES: usize = 10;
+
+const SCORES_FILE: &str = "scores.toml";
+
+/// Bumped if the file layout ever changes in a way serde defaults can't absorb
+const FORMAT_VERSION: u32 = 1;
+
+/// One finished game on the high score table. Every field has a default so
+/// files written by older or newer versions still load
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    pub time_ms: u64,
+    pub pps: f64,  // Pieces per second
+    pub date: u64, // Seconds since the Unix epoch
+}
+
+impl ScoreEntry {
+    pub fn from_game(game: &Game, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            score: game.score,
+            lines: game.lines,
+            level: game.level,
+            time_ms: game.time.as_millis() as u64,
+            pps: game.stats.pieces_per_second(game.time),
+            date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+        }
+    }
+
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms)
+    }
+}
+
+/// Top results per mode and ruleset, persisted as TOML in the data directory
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    pub version: u32,
+    /// Keyed by `mode/ruleset`, see `HighScores::key`
+    pub tables: BTreeMap<String, Vec<ScoreEntry>>,
+}
+
+impl Default for HighScores {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            tables: BTreeMap::new(),
+        }
+    }
+}
+
+impl HighScores {
+    /// Load the high scores, starting empty when there are none yet. An error
+    /// means the file exists but can't be read or parsed, and must not be saved over
+    pub fn load() -> io::Result<Self> {
+        match config::read_data(SCORES_FILE)? {
+            Some(contents) => toml::from_str(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Move an unreadable high score file aside to `scores.toml.bak`, or
+    /// `scores.toml.2.bak` and so on when older backups are still there, so
+    /// saving a new table can't destroy it. Returns the backup's name
+    pub fn back_up() -> io::Result<String> {
+        for count in 1.. {
+            let backup = match count {
+                1 => format!("{}.bak", SCORES_FILE),
+                _ => format!("{}.{}.bak", SCORES_FILE, count),
+            };
+            match config::rename_data(SCORES_FILE, &backup) {
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                result => return result.map(|()| backup),
+            }
+        }
+        unreachable!("a free backup name is always found")
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        config::write_data(SCORES_FILE, &contents)
+    }
+
+    /// `mode/ruleset`, with the board size appended when it isn't the standard
+    /// one, e.g. `sprint/guideline/7-bag/100/8x16+4`. Standard boards keep the
+    /// plain key so tables saved before board sizes counted still apply
+    fn key(mode: GameMode, board: BoardSize, ruleset: &Ruleset) -> String {
+        let key = format!("{}/{}", mode.name(), ruleset.name());
+        if board == BoardSize::default() {
+            key
+        } else {
+            format!("{}/{}", key, board_name(board))
+        }
+    }
+
+    pub fn entries(&self, mode: GameMode, board: BoardSize, ruleset: &Ruleset) -> &[ScoreEntry] {
+        self.tables
+            .get(&Self::key(mode, board, ruleset))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Add a finished game to its table, returning its 0-based rank if it made
+    /// the top `MAX_ENTRIES`. Ties go to the older entry
+    pub fn record(&mut self, game: &Game, name: &str) -> Option<usize> {
+        if !game.mode.is_ranked(game) {
+            return None;
+        }
+
+        let entry = ScoreEntry::from_game(game, name);
+        let table = self
+            .tables
+            .entry(Self::key(game.mode, game.board, &game.ruleset))
+            .or_default();
+
+        let rank = if game.mode.ranks_by_time() {
+            table.partition_point(|other| other.time_ms <= entry.time_ms)
+        } else {
+            table.partition_point(|other| other.score >= entry.score)
+        };
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+
+        table.insert(rank, entry);
+        table.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+/// Board size as `widthxheight+buffer`, e.g. `10x20+20`
+pub fn board_name(board: BoardSize) -> String {
+    format!("{}x{}+{}", board.width, board.height, board.buffer)
+}
+
+/// Format seconds since the Unix epoch as a `YYYY-MM-DD` date in UTC
+pub fn format_date(seconds: u64) -> String {
+    // Civil date from a day count, after Howard Hinnant's `civil_from_days`
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished(mode: GameMode, score: u32, time_ms: u64) -> Game {
+        let mut game = Game::new(BoardSize::default(), mode, Ruleset::default(), 0);
+        game.score = score;
+        game.lines = mode.line_goal().unwrap_or(0);
+        game.time = Duration::from_millis(time_ms);
+        game
+    }
+
+    #[test]
+    fn keeps_the_best_ten_scores() {
+        let mut scores = HighScores::default();
+        for score in 0..15 {
+            scores.record(&finished(GameMode::Ultra, score * 100, 120_000), "a");
+        }
+
+        let entries = scores.entries(GameMode::Ultra, BoardSize::default(), &Ruleset::default());
+        let kept: Vec<u32> = entries.iter().map(|entry| entry.score).collect();
+        assert_eq!(
+            kept,
+            (5..15).rev().map(|score| score * 100).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            scores.record(&finished(GameMode::Ultra, 0, 120_000), "a"),
+            None
+        );
+    }
+
+    #[test]
+    fn races_rank_by_time() {
+        let mut scores = HighScores::default();
+        scores.record(&finished(GameMode::Sprint, 0, 60_000), "slow");
+        let rank = scores.record(&finished(GameMode::Sprint, 0, 45_000), "fast");
+
+        assert_eq!(rank, Some(0));
+        assert_eq!(
+            scores.entries(GameMode::Sprint, BoardSize::default(), &Ruleset::default())[0].name,
+            "fast"
+        );
+    }
+
+    #[test]
+    fn unfinished_races_are_not_ranked() {
+        let mut scores = HighScores::default();
+        let mut game = finished(GameMode::Sprint, 0, 30_000);
+        game.lines = 20;
+
+        assert_eq!(scores.record(&game, "a"), None);
+    }
+
+    #[test]
+    fn loads_files_with_unknown_and_missing_fields() {
+        let contents = r#"
+            version = 7
+            future_setting = true
+
+            [[tables."ultra/guideline/7-bag/100"]]
+            name = "a"
+            score = 1200
+            replay = "somewhere"
+        "#;
+        let scores: HighScores = toml::from_str(contents).unwrap();
+        let entries = scores.entries(GameMode::Ultra, BoardSize::default(), &Ruleset::default());
+
+        assert_eq!(entries[0].score, 1200);
+        assert_eq!(entries[0].lines, 0);
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_792_281_600), "2026-10-18");
+    }
+
+    #[test]
+    fn other_board_sizes_rank_apart() {
+        let mut scores = HighScores::default();
+        let small = BoardSize {
+            width: 4,
+            height: 20,
+            buffer: 20,
+        };
+        let mut game = Game::new(small, GameMode::Ultra, Ruleset::default(), 0);
+        game.score = 5000;
+        scores.record(&game, "a");
+
+        let ruleset = Ruleset::default();
+        assert!(scores
+            .entries(GameMode::Ultra, BoardSize::default(), &ruleset)
+            .is_empty());
+        assert_eq!(
+            scores.entries(GameMode::Ultra, small, &ruleset)[0].score,
+            5000
+        );
+        assert!(scores
+            .tables
+            .contains_key("ultra/guideline/7-bag/100/4x20+20"));
+    }
+
+    #[test]
+    fn unreadable_files_are_an_error() {
+        assert!(toml::from_str::<HighScores>("tables = 3").is_err());
+    }
+}
@@ -0,0 +1,444 @@
+use crate::config;
+use crate::game::control::{Ruleset, NEXT_QUEUE_SIZE};
+use crate::game::gravity::Gravity;
+use crate::game::randomizer::RandomizerKind;
+use crate::input::InputConfig;
+use crate::keys::{key_from_name, key_name, Action, KeyBindings};
+use crate::renderer::theme::Theme;
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::time::Duration;
+use toml::{Table, Value};
+
+const MAX_NAME_LENGTH: usize = 16;
+
+/// Everything the player can tune, persisted as TOML in the config directory
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub das_ms: u64,
+    pub arr_ms: u64,
+    pub soft_drop_factor: u32,
+    pub gravity: Gravity,
+    pub randomizer: RandomizerKind,
+    /// Chance in percent that a garbage hole changes column
+    pub messiness: u32,
+    pub lock_delay_ms: u64,
+    /// Moves and rotations that may restart the lock delay
+    pub max_lock_resets: u32,
+    pub ghost: bool,
+    pub next_queue: usize,
+    pub theme: Theme,
+    /// Name recorded with high scores
+    pub name: String,
+    /// Seed for every game of this session, random per game when unset. Only set
+    /// from the menu or command line, it isn't saved
+    #[serde(skip)]
+    pub seed: Option<u64>,
+    /// Key names per action, see `KeyBindings::from_names`
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let input = InputConfig::default();
+        let ruleset = Ruleset::default();
+        Self {
+            das_ms: input.das.as_millis() as u64,
+            arr_ms: input.arr.as_millis() as u64,
+            soft_drop_factor: input.soft_drop_factor,
+            gravity: Gravity::Guideline,
+            randomizer: RandomizerKind::default(),
+            messiness: ruleset.messiness,
+            lock_delay_ms: ruleset.lock_delay_ms,
+            max_lock_resets: ruleset.max_lock_resets,
+            ghost: true,
+            next_queue: 5,
+            theme: Theme::default(),
+            name: std::env::var("USER").unwrap_or_else(|_| "player".to_string()),
+            seed: None,
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Load the settings file, using the defaults when it doesn't exist yet. An
+    /// error means the file can't be read or isn't valid TOML, and it must not
+    /// be saved over
+    pub fn load() -> io::Result<Self> {
+        match config::read()? {
+            Some(contents) => Self::from_toml(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Parse settings field by field: a value that doesn't fit falls back to its
+    /// default without taking the rest of the file with it, and so does each bad
+    /// `[keys]` entry
+    fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        let file: Table = toml::from_str(contents)?;
+        let mut table = Table::try_from(Self::default()).expect("settings serialize to a table");
+        let parses = |table: &Table| Value::Table(table.clone()).try_into::<Self>().is_ok();
+
+        for (field, value) in file {
+            let value = match value {
+                Value::Table(keys) if field == "keys" => Value::Table(
+                    keys.into_iter()
+                        .filter(|(_, names)| {
+                            names
+                                .as_array()
+                                .is_some_and(|names| names.iter().all(Value::is_str))
+                        })
+                        .collect(),
+                ),
+                value => value,
+            };
+            let previous = table.insert(field.clone(), value);
+            if !parses(&table) {
+                match previous {
+                    Some(previous) => table.insert(field, previous),
+                    None => table.remove(&field),
+                };
+            }
+        }
+        Value::Table(table).try_into()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        config::write(&contents)
+    }
+
+    pub fn input_config(&self) -> InputConfig {
+        InputConfig {
+            das: Duration::from_millis(self.das_ms),
+            arr: Duration::from_millis(self.arr_ms),
+            soft_drop_factor: self.soft_drop_factor,
+        }
+    }
+
+    pub fn ruleset(&self) -> Ruleset {
+        Ruleset {
+            gravity: self.gravity,
+            randomizer: self.randomizer,
+            messiness: self.messiness,
+            lock_delay_ms: self.lock_delay_ms,
+            max_lock_resets: self.max_lock_resets,
+        }
+    }
+
+    pub fn key_bindings(&self) -> KeyBindings {
+        KeyBindings::from_names(&self.keys)
+    }
+}
+
+/// Settings given on the command line. They apply on top of the saved settings
+/// for this run only and never end up in the config file
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    pub gravity: Option<Gravity>,
+    pub randomizer: Option<RandomizerKind>,
+    pub messiness: Option<u32>,
+    pub lock_delay_ms: Option<u64>,
+    pub max_lock_resets: Option<u32>,
+    pub das_ms: Option<u64>,
+    pub arr_ms: Option<u64>,
+    pub soft_drop_factor: Option<u32>,
+}
+
+impl Overrides {
+    /// The settings to play with: the saved ones with the overrides on top
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        settings.gravity = self.gravity.unwrap_or(settings.gravity);
+        settings.randomizer = self.randomizer.unwrap_or(settings.randomizer);
+        settings.messiness = self.messiness.unwrap_or(settings.messiness);
+        settings.lock_delay_ms = self.lock_delay_ms.unwrap_or(settings.lock_delay_ms);
+        settings.max_lock_resets = self.max_lock_resets.unwrap_or(settings.max_lock_resets);
+        settings.das_ms = self.das_ms.unwrap_or(settings.das_ms);
+        settings.arr_ms = self.arr_ms.unwrap_or(settings.arr_ms);
+        settings.soft_drop_factor = self.soft_drop_factor.unwrap_or(settings.soft_drop_factor);
+        settings
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Item {
+    Das,
+    Arr,
+    SoftDropFactor,
+    Gravity,
+    Randomizer,
+    Messiness,
+    LockDelay,
+    LockResets,
+    Ghost,
+    NextQueue,
+    Theme,
+    Name,
+    Seed,
+    Key(Action),
+    Back,
+}
+
+fn items() -> Vec<Item> {
+    let mut items = vec![
+        Item::Das,
+        Item::Arr,
+        Item::SoftDropFactor,
+        Item::Gravity,
+        Item::Randomizer,
+        Item::Messiness,
+        Item::LockDelay,
+        Item::LockResets,
+        Item::Ghost,
+        Item::NextQueue,
+        Item::Theme,
+        Item::Name,
+        Item::Seed,
+    ];
+    items.extend(Action::ALL.into_iter().map(Item::Key));
+    items.push(Item::Back);
+    items
+}
+
+/// Cycle through a list of options, wrapping at both ends
+pub fn cycle<T: Copy + PartialEq>(options: &[T], current: T, step: isize) -> T {
+    let index = options
+        .iter()
+        .position(|&option| option == current)
+        .unwrap_or(0);
+    let len = options.len() as isize;
+    options[(index as isize + step).rem_euclid(len) as usize]
+}
+
+/// State of the settings screen: Up/Down pick a row, Left/Right change its value
+/// and Enter on a key binding waits for the new key
+#[derive(Debug, Default)]
+pub struct SettingsMenu {
+    pub selected: usize,
+    pub rebinding: bool,
+}
+
+impl SettingsMenu {
+    /// Label and current value for every row of the menu
+    pub fn rows(&self, settings: &Settings) -> Vec<(String, String)> {
+        let bindings = settings.key_bindings();
+        items()
+            .into_iter()
+            .map(|item| match item {
+                Item::Das => ("DAS".to_string(), format!("{} ms", settings.das_ms)),
+                Item::Arr => ("ARR".to_string(), format!("{} ms", settings.arr_ms)),
+                Item::SoftDropFactor => (
+                    "Soft drop factor".to_string(),
+                    format!("{}x", settings.soft_drop_factor),
+                ),
+                Item::Gravity => ("Gravity".to_string(), settings.gravity.name().to_string()),
+                Item::Randomizer => (
+                    "Randomizer".to_string(),
+                    settings.randomizer.name().to_string(),
+                ),
+                Item::Messiness => (
+                    "Garbage messiness".to_string(),
+                    format!("{}%", settings.messiness),
+                ),
+                Item::LockDelay => (
+                    "Lock delay".to_string(),
+                    format!("{} ms", settings.lock_delay_ms),
+                ),
+                Item::LockResets => (
+                    "Lock resets".to_string(),
+                    settings.max_lock_resets.to_string(),
+                ),
+                Item::Ghost => (
+                    "Ghost piece".to_string(),
+                    if settings.ghost { "on" } else { "off" }.to_string(),
+                ),
+                Item::NextQueue => ("Next queue".to_string(), settings.next_queue.to_string()),
+                Item::Theme => ("Theme".to_string(), settings.theme.name().to_string()),
+                Item::Name => ("Name".to_string(), settings.name.clone()),
+                Item::Seed => (
+                    "Seed".to_string(),
+                    settings
+                        .seed
+                        .map_or("random".to_string(), |seed| seed.to_string()),
+                ),
+                Item::Key(action) => (format!("Key: {}", action.name()), bindings.describe(action)),
+                Item::Back => ("Save and go back".to_string(), String::new()),
+            })
+            .collect()
+    }
+
+    /// Handle a key press, returning `false` once the menu should close
+    pub fn handle_key(&mut self, code: KeyCode, settings: &mut Settings) -> bool {
+        let items = items();
+
+        if self.rebinding {
+            if let Item::Key(action) = items[self.selected] {
+                if code != KeyCode::Esc {
+                    // The key moves to this action, other entries let go of it
+                    let name = key_name(code);
+                    for keys in settings.keys.values_mut() {
+                        keys.retain(|key| key_from_name(key) != key_from_name(&name));
+                    }
+                    settings.keys.retain(|_, keys| !keys.is_empty());
+                    settings.keys.insert(action.name().to_string(), vec![name]);
+                }
+            }
+            self.rebinding = false;
+            return true;
+        }
+
+        // The name row takes typed text, Backspace removes the last character
+        if items[self.selected] == Item::Name {
+            match code {
+                KeyCode::Char(c) if settings.name.chars().count() < MAX_NAME_LENGTH => {
+                    settings.name.push(c);
+                    return true;
+                }
+                KeyCode::Backspace => {
+                    settings.name.pop();
+                    return true;
+                }
+                _ => {}
+            }
+        }
+
+        // The seed row takes typed digits, Backspace removes the last one
+        if items[self.selected] == Item::Seed {
+            match code {
+                KeyCode::Char(digit @ '0'..='9') => {
+                    let digit = u64::from(digit as u8 - b'0');
+                    let seed = settings.seed.unwrap_or(0);
+                    // Digits that would overflow are ignored
+                    let typed = seed
+                        .checked_mul(10)
+                        .and_then(|seed| seed.checked_add(digit));
+                    settings.seed = Some(typed.unwrap_or(seed));
+                    return true;
+                }
+                KeyCode::Backspace => {
+                    settings.seed = settings
+                        .seed
+                        .filter(|&seed| seed >= 10)
+                        .map(|seed| seed / 10);
+                    return true;
+                }
+                _ => {}
+            }
+        }
+
+        match code {
+            KeyCode::Up => self.selected = (self.selected + items.len() - 1) % items.len(),
+            KeyCode::Down => self.selected = (self.selected + 1) % items.len(),
+            KeyCode::Left => Self::adjust(items[self.selected], settings, -1),
+            KeyCode::Right => Self::adjust(items[self.selected], settings, 1),
+            KeyCode::Enter => match items[self.selected] {
+                Item::Key(_) => self.rebinding = true,
+                Item::Back => return false,
+                item => Self::adjust(item, settings, 1),
+            },
+            KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn adjust(item: Item, settings: &mut Settings, step: isize) {
+        match item {
+            Item::Das => settings.das_ms = settings.das_ms.saturating_add_signed(10 * step as i64),
+            Item::Arr => settings.arr_ms = settings.arr_ms.saturating_add_signed(step as i64),
+            Item::SoftDropFactor => {
+                settings.soft_drop_factor = settings
+                    .soft_drop_factor
+                    .saturating_add_signed(step as i32)
+                    .max(1)
+            }
+            Item::Gravity => settings.gravity = cycle(&Gravity::ALL, settings.gravity, step),
+            Item::Randomizer => {
+                settings.randomizer = cycle(&RandomizerKind::ALL, settings.randomizer, step)
+            }
+            Item::Messiness => {
+                settings.messiness = settings
+                    .messiness
+                    .saturating_add_signed(10 * step as i32)
+                    .min(100)
+            }
+            Item::LockDelay => {
+                settings.lock_delay_ms = settings
+                    .lock_delay_ms
+                    .saturating_add_signed(10 * step as i64)
+            }
+            Item::LockResets => {
+                settings.max_lock_resets =
+                    settings.max_lock_resets.saturating_add_signed(step as i32)
+            }
+            Item::Ghost => settings.ghost = !settings.ghost,
+            Item::NextQueue => {
+                settings.next_queue = settings
+                    .next_queue
+                    .saturating_add_signed(step)
+                    .clamp(1, NEXT_QUEUE_SIZE)
+            }
+            Item::Theme => settings.theme = cycle(&Theme::ALL, settings.theme, step),
+            Item::Name | Item::Seed | Item::Key(_) | Item::Back => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_fields_fall_back_one_at_a_time() {
+        let settings = Settings::from_toml(
+            r#"
+            das_ms = "fast"
+            arr_ms = 0
+            gravity = "sideways"
+            theme = "monochrome"
+
+            [keys]
+            hold = ["Tab"]
+            pause = "p"
+            quit = [1, 2]
+            "#,
+        )
+        .unwrap();
+        let defaults = Settings::default();
+
+        assert_eq!(settings.das_ms, defaults.das_ms);
+        assert_eq!(settings.arr_ms, 0);
+        assert_eq!(settings.gravity, defaults.gravity);
+        assert_eq!(settings.theme, Theme::Monochrome);
+        assert_eq!(settings.keys.len(), 1);
+        assert_eq!(settings.keys["hold"], vec!["Tab".to_string()]);
+    }
+
+    #[test]
+    fn broken_files_are_an_error() {
+        assert!(Settings::from_toml("das_ms = ").is_err());
+    }
+
+    #[test]
+    fn overrides_leave_the_saved_settings_alone() {
+        let saved = Settings::default();
+        let overrides = Overrides {
+            gravity: Some(Gravity::TwentyG),
+            das_ms: Some(90),
+            ..Overrides::default()
+        };
+        let session = overrides.apply(&saved);
+
+        assert_eq!(session.gravity, Gravity::TwentyG);
+        assert_eq!(session.das_ms, 90);
+        assert_eq!(session.arr_ms, saved.arr_ms);
+        assert_eq!(saved, Settings::default());
+    }
+}